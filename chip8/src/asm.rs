@@ -0,0 +1,521 @@
+use crate::{Instruction, Platform, START_ADDR};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+
+// The number of constants that can refer to each other before the definition is considered circular
+const MAX_CONSTANT_DEPTH: usize = 16;
+
+// The names which can't be used for labels or constants, as they are operands of the instructions
+const RESERVED: [&str; 10] = ["I", "DT", "ST", "K", "F", "HF", "B", "R", "LONG", "DB"];
+
+// The mnemonics understood by the assembler, to tell apart unknown mnemonics from invalid operands
+const MNEMONICS: [&str; 33] = [
+    "NOP", "CLS", "RET", "SCD", "SCU", "SCR", "SCL", "EXIT", "LOW", "HIGH", "JP", "CALL", "SE",
+    "SNE", "SAVE", "LOAD", "LD", "ADD", "OR", "AND", "XOR", "SUB", "SHR", "SUBN", "SHL", "RND",
+    "DRW", "SKP", "SKNP", "PLANE", "AUDIO", "PITCH", "DB",
+];
+
+// An error in the source of a program, with the position it was found at
+// Both the line and the column start at 1
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AsmError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl AsmError {
+    fn new(line: usize, column: usize, message: impl Into<String>) -> Self {
+        AsmError {
+            line,
+            column,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.column, self.message)
+    }
+}
+
+impl Error for AsmError {}
+
+// A piece of a line, with the column it starts at
+#[derive(Debug, Clone, Copy)]
+struct Token<'a> {
+    text: &'a str,
+    column: usize,
+}
+
+// An instruction or a `db` directive, along with the address it is assembled at
+struct Statement<'a> {
+    line: usize,
+    mnemonic: Token<'a>,
+    operands: Vec<Token<'a>>,
+}
+
+// The value of an operand, once the labels and constants are resolved
+#[derive(Debug, Clone, Copy)]
+struct Number {
+    value: i64,
+    line: usize,
+    column: usize,
+}
+
+impl Number {
+    fn fit(&self, min: i64, max: i64, what: &str) -> Result<i64, AsmError> {
+        if self.value < min || self.value > max {
+            return Err(AsmError::new(
+                self.line,
+                self.column,
+                format!("{} out of range: {}", what, self.value),
+            ));
+        }
+        Ok(self.value)
+    }
+
+    // A 12 bit address, for NNN
+    fn addr(&self) -> Result<u16, AsmError> {
+        self.fit(0, 0xFFF, "address").map(|value| value as u16)
+    }
+
+    // A 16 bit address, for F000 NNNN
+    fn long_addr(&self) -> Result<u16, AsmError> {
+        self.fit(0, 0xFFFF, "address").map(|value| value as u16)
+    }
+
+    // A byte, for NN and the data directives
+    // Negative values are allowed as well, so that `ADD V0, -1` can be written
+    fn byte(&self) -> Result<u8, AsmError> {
+        self.fit(-0x80, 0xFF, "byte").map(|value| value as u8)
+    }
+
+    // A 4 bit value, for N
+    fn nibble(&self) -> Result<u8, AsmError> {
+        self.fit(0, 0xF, "value").map(|value| value as u8)
+    }
+}
+
+// An operand of an instruction
+#[derive(Debug, Clone, Copy)]
+enum Operand {
+    V(u8),
+    I,
+    IndirectI,
+    Dt,
+    St,
+    K,
+    F,
+    Hf,
+    B,
+    R,
+    Long(Number),
+    Value(Number),
+}
+
+// Assemble the source of a program into a ROM, which can be given to `CPU::load` as is on the given platform
+//
+// Each line holds an optional label, followed by an instruction, a directive or a constant, and an optional comment:
+//     ; Draw the sprite at the center of the screen
+//     SPEED = 2
+//     start:  LD I, sprite
+//             LD V0, 30
+//             DRW V0, V1, 5
+//             ADD V0, SPEED
+//             JP start
+//     sprite: db 0xF0, 0x90, 0x90, 0x90, 0xF0
+//
+// The mnemonics are the ones printed by the disassembler, and are case insensitive
+// The values can be written in decimal, in hexadecimal with 0x or in binary with 0b,
+// and can be combined with the labels and constants using + and -
+pub fn assemble(source: &str, platform: Platform) -> Result<Vec<u8>, AsmError> {
+    Assembler::parse(source, platform)?.encode()
+}
+
+struct Assembler<'a> {
+    statements: Vec<Statement<'a>>,
+    labels: BTreeMap<&'a str, u16>,
+    constants: BTreeMap<&'a str, (usize, Token<'a>)>,
+    size: usize,
+    platform: Platform, // The instructions of other platforms are rejected
+}
+
+impl<'a> Assembler<'a> {
+    // First pass: split the lines into statements, and find the address of every label
+    fn parse(source: &'a str, platform: Platform) -> Result<Self, AsmError> {
+        let mut asm = Assembler {
+            statements: Vec::new(),
+            labels: BTreeMap::new(),
+            constants: BTreeMap::new(),
+            size: 0,
+            platform,
+        };
+
+        for (index, text) in source.lines().enumerate() {
+            let line = index + 1;
+            let text = match text.find(';') {
+                Some(comment) => &text[..comment],
+                None => text,
+            };
+            let mut rest = token(text, 0, text.len());
+
+            // Any number of labels can come first
+            while let Some(colon) = rest.text.find(':') {
+                let label = token(text, rest.column - 1, rest.column - 1 + colon);
+                asm.define(line, label)?;
+                asm.labels
+                    .insert(label.text, (START_ADDR as usize + asm.size) as u16);
+                rest = token(text, rest.column + colon, text.len());
+            }
+            if rest.text.is_empty() {
+                continue;
+            }
+
+            // A constant, in the form NAME = VALUE
+            if let Some(equals) = rest.text.find('=') {
+                let name = token(text, rest.column - 1, rest.column - 1 + equals);
+                let value = token(text, rest.column + equals, text.len());
+                asm.define(line, name)?;
+                if value.text.is_empty() {
+                    return Err(AsmError::new(line, value.column, "expected a value"));
+                }
+                asm.constants.insert(name.text, (line, value));
+                continue;
+            }
+
+            // An instruction or a directive, followed by its operands separated by commas
+            let split = rest
+                .text
+                .find(char::is_whitespace)
+                .unwrap_or(rest.text.len());
+            let mnemonic = token(text, rest.column - 1, rest.column - 1 + split);
+            let mut operands = Vec::new();
+            let mut start = mnemonic.column - 1 + split;
+            if !text[start..].trim().is_empty() {
+                for part in text[start..].split(',') {
+                    let operand = token(text, start, start + part.len());
+                    if operand.text.is_empty() {
+                        return Err(AsmError::new(line, operand.column, "expected an operand"));
+                    }
+                    operands.push(operand);
+                    start += part.len() + 1;
+                }
+            }
+
+            let statement = Statement {
+                line,
+                mnemonic,
+                operands,
+            };
+            asm.size += statement.size();
+            if START_ADDR as usize + asm.size > platform.ram_size() {
+                return Err(AsmError::new(line, mnemonic.column, "program too large"));
+            }
+            asm.statements.push(statement);
+        }
+
+        Ok(asm)
+    }
+
+    // Second pass: resolve the operands, and encode every statement
+    fn encode(&self) -> Result<Vec<u8>, AsmError> {
+        let mut rom = Vec::with_capacity(self.size);
+        for statement in &self.statements {
+            let mnemonic = statement.mnemonic.text.to_ascii_uppercase();
+            if mnemonic == "DB" {
+                for operand in &statement.operands {
+                    rom.push(self.number(statement.line, *operand)?.byte()?);
+                }
+                continue;
+            }
+
+            let operands = statement
+                .operands
+                .iter()
+                .map(|operand| self.operand(statement.line, *operand))
+                .collect::<Result<Vec<_>, _>>()?;
+            let instruction = self.instruction(statement, &mnemonic, &operands)?;
+            if !self.platform.supports(instruction.platform()) {
+                return Err(AsmError::new(
+                    statement.line,
+                    statement.mnemonic.column,
+                    format!(
+                        "{} needs {}, the program is for {}",
+                        statement.mnemonic.text,
+                        instruction.platform(),
+                        self.platform
+                    ),
+                ));
+            }
+            rom.extend_from_slice(&instruction.encode().to_be_bytes());
+            if let [_, Operand::Long(nnnn)] = operands.as_slice() {
+                rom.extend_from_slice(&nnnn.long_addr()?.to_be_bytes());
+            }
+        }
+        Ok(rom)
+    }
+
+    // Check that a label or a constant can be defined with the given name
+    fn define(&self, line: usize, name: Token<'a>) -> Result<(), AsmError> {
+        let valid = name
+            .text
+            .starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+            && name
+                .text
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if !valid {
+            return Err(AsmError::new(
+                line,
+                name.column,
+                format!("invalid name: {:?}", name.text),
+            ));
+        }
+        let upper = name.text.to_ascii_uppercase();
+        if register(&upper).is_some() || RESERVED.contains(&upper.as_str()) {
+            return Err(AsmError::new(
+                line,
+                name.column,
+                format!("reserved name: {}", name.text),
+            ));
+        }
+        if self.labels.contains_key(name.text) || self.constants.contains_key(name.text) {
+            return Err(AsmError::new(
+                line,
+                name.column,
+                format!("{} is already defined", name.text),
+            ));
+        }
+        Ok(())
+    }
+
+    fn operand(&self, line: usize, operand: Token<'a>) -> Result<Operand, AsmError> {
+        let upper = operand.text.to_ascii_uppercase();
+        if let Some(x) = register(&upper) {
+            return Ok(Operand::V(x));
+        }
+        let keyword = match upper.as_str() {
+            "I" => Some(Operand::I),
+            "[I]" => Some(Operand::IndirectI),
+            "DT" => Some(Operand::Dt),
+            "ST" => Some(Operand::St),
+            "K" => Some(Operand::K),
+            "F" => Some(Operand::F),
+            "HF" => Some(Operand::Hf),
+            "B" => Some(Operand::B),
+            "R" => Some(Operand::R),
+            _ => None,
+        };
+        if let Some(keyword) = keyword {
+            return Ok(keyword);
+        }
+        // LONG is followed by the 16 bit address of F000 NNNN
+        if upper.starts_with("LONG") && upper[4..].starts_with(char::is_whitespace) {
+            let addr = token(operand.text, 4, operand.text.len());
+            let addr = Token {
+                text: addr.text,
+                column: operand.column - 1 + addr.column,
+            };
+            return Ok(Operand::Long(self.number(line, addr)?));
+        }
+        Ok(Operand::Value(self.number(line, operand)?))
+    }
+
+    fn number(&self, line: usize, operand: Token<'a>) -> Result<Number, AsmError> {
+        Ok(Number {
+            value: self.eval(line, operand, 0)?,
+            line,
+            column: operand.column,
+        })
+    }
+
+    // Evaluate a sum of numbers, labels and constants, such as `sprite + 5`
+    fn eval(&self, line: usize, expr: Token<'a>, depth: usize) -> Result<i64, AsmError> {
+        let mut total: i64 = 0;
+        let mut sign = 1;
+        let mut offset = 0;
+        loop {
+            let rest = &expr.text[offset..];
+            let skipped = rest.len() - rest.trim_start().len();
+            offset += skipped;
+            let rest = &expr.text[offset..];
+            // A leading minus negates the first term
+            if offset == 0 && rest.starts_with('-') {
+                sign = -1;
+                offset += 1;
+                continue;
+            }
+            let end = rest.find(['+', '-']).unwrap_or(rest.len());
+            let term = Token {
+                text: rest[..end].trim_end(),
+                column: expr.column + offset,
+            };
+            if term.text.is_empty() {
+                return Err(AsmError::new(line, term.column, "expected a value"));
+            }
+            total = self
+                .term(line, term, depth)?
+                .checked_mul(sign)
+                .and_then(|value| total.checked_add(value))
+                .ok_or_else(|| AsmError::new(line, term.column, "number out of range"))?;
+            if end == rest.len() {
+                return Ok(total);
+            }
+            sign = if rest[end..].starts_with('+') { 1 } else { -1 };
+            offset += end + 1;
+        }
+    }
+
+    fn term(&self, line: usize, term: Token<'a>, depth: usize) -> Result<i64, AsmError> {
+        if term.text.starts_with(|c: char| c.is_ascii_digit()) {
+            let text = term.text.to_ascii_lowercase();
+            let parsed = if let Some(hex) = text.strip_prefix("0x") {
+                i64::from_str_radix(hex, 16)
+            } else if let Some(bin) = text.strip_prefix("0b") {
+                i64::from_str_radix(bin, 2)
+            } else {
+                text.parse()
+            };
+            return parsed.map_err(|_| {
+                AsmError::new(line, term.column, format!("invalid number: {}", term.text))
+            });
+        }
+        if let Some(&addr) = self.labels.get(term.text) {
+            return Ok(addr as i64);
+        }
+        if let Some(&(definition, value)) = self.constants.get(term.text) {
+            if depth >= MAX_CONSTANT_DEPTH {
+                return Err(AsmError::new(
+                    line,
+                    term.column,
+                    format!("{} is defined in terms of itself", term.text),
+                ));
+            }
+            return self.eval(definition, value, depth + 1);
+        }
+        Err(AsmError::new(
+            line,
+            term.column,
+            format!("undefined label or constant: {}", term.text),
+        ))
+    }
+
+    fn instruction(
+        &self,
+        statement: &Statement,
+        mnemonic: &str,
+        operands: &[Operand],
+    ) -> Result<Instruction, AsmError> {
+        use Operand::*;
+
+        let instruction = match (mnemonic, operands) {
+            ("NOP", []) => Instruction::Nop,
+            ("CLS", []) => Instruction::Cls,
+            ("RET", []) => Instruction::Ret,
+            ("SCD", [Value(n)]) => Instruction::ScrollDown(n.nibble()?),
+            ("SCU", [Value(n)]) => Instruction::ScrollUp(n.nibble()?),
+            ("SCR", []) => Instruction::ScrollRight,
+            ("SCL", []) => Instruction::ScrollLeft,
+            ("EXIT", []) => Instruction::Exit,
+            ("LOW", []) => Instruction::Low,
+            ("HIGH", []) => Instruction::High,
+            ("JP", [Value(nnn)]) => Instruction::Jp(nnn.addr()?),
+            ("JP", [V(0), Value(nnn)]) => Instruction::JpV0(nnn.addr()?),
+            ("CALL", [Value(nnn)]) => Instruction::Call(nnn.addr()?),
+            ("SE", [V(x), Value(nn)]) => Instruction::SeVxByte(*x, nn.byte()?),
+            ("SE", [V(x), V(y)]) => Instruction::SeVxVy(*x, *y),
+            ("SNE", [V(x), Value(nn)]) => Instruction::SneVxByte(*x, nn.byte()?),
+            ("SNE", [V(x), V(y)]) => Instruction::SneVxVy(*x, *y),
+            ("SAVE", [V(x), V(y)]) => Instruction::SaveVxVy(*x, *y),
+            ("LOAD", [V(x), V(y)]) => Instruction::LoadVxVy(*x, *y),
+            ("LD", [V(x), Value(nn)]) => Instruction::LdVxByte(*x, nn.byte()?),
+            ("LD", [V(x), V(y)]) => Instruction::LdVxVy(*x, *y),
+            ("LD", [I, Value(nnn)]) => Instruction::LdI(nnn.addr()?),
+            ("LD", [I, Long(_)]) => Instruction::LdILong,
+            ("LD", [V(x), Dt]) => Instruction::LdVxDt(*x),
+            ("LD", [V(x), K]) => Instruction::LdVxK(*x),
+            ("LD", [Dt, V(x)]) => Instruction::LdDtVx(*x),
+            ("LD", [St, V(x)]) => Instruction::LdStVx(*x),
+            ("LD", [F, V(x)]) => Instruction::LdFVx(*x),
+            ("LD", [Hf, V(x)]) => Instruction::LdHfVx(*x),
+            ("LD", [B, V(x)]) => Instruction::LdBVx(*x),
+            ("LD", [IndirectI, V(x)]) => Instruction::LdIVx(*x),
+            ("LD", [V(x), IndirectI]) => Instruction::LdVxI(*x),
+            ("LD", [R, V(x)]) => Instruction::LdRVx(*x),
+            ("LD", [V(x), R]) => Instruction::LdVxR(*x),
+            ("ADD", [V(x), Value(nn)]) => Instruction::AddVxByte(*x, nn.byte()?),
+            ("ADD", [V(x), V(y)]) => Instruction::AddVxVy(*x, *y),
+            ("ADD", [I, V(x)]) => Instruction::AddIVx(*x),
+            ("OR", [V(x), V(y)]) => Instruction::OrVxVy(*x, *y),
+            ("AND", [V(x), V(y)]) => Instruction::AndVxVy(*x, *y),
+            ("XOR", [V(x), V(y)]) => Instruction::XorVxVy(*x, *y),
+            ("SUB", [V(x), V(y)]) => Instruction::SubVxVy(*x, *y),
+            ("SUBN", [V(x), V(y)]) => Instruction::SubnVxVy(*x, *y),
+            // The shifts can be given a single register, which is then shifted in place
+            ("SHR", [V(x)]) => Instruction::ShrVxVy(*x, *x),
+            ("SHR", [V(x), V(y)]) => Instruction::ShrVxVy(*x, *y),
+            ("SHL", [V(x)]) => Instruction::ShlVxVy(*x, *x),
+            ("SHL", [V(x), V(y)]) => Instruction::ShlVxVy(*x, *y),
+            ("RND", [V(x), Value(nn)]) => Instruction::Rnd(*x, nn.byte()?),
+            ("DRW", [V(x), V(y), Value(n)]) => Instruction::Drw(*x, *y, n.nibble()?),
+            ("SKP", [V(x)]) => Instruction::Skp(*x),
+            ("SKNP", [V(x)]) => Instruction::Sknp(*x),
+            ("PLANE", [Value(n)]) => Instruction::Plane(n.fit(0, 3, "plane")? as u8),
+            ("AUDIO", []) => Instruction::Audio,
+            ("PITCH", [V(x)]) => Instruction::Pitch(*x),
+            _ => {
+                let message = if MNEMONICS.contains(&mnemonic) {
+                    format!("invalid operands for {}", mnemonic)
+                } else {
+                    format!("unknown instruction: {}", statement.mnemonic.text)
+                };
+                return Err(AsmError::new(
+                    statement.line,
+                    statement.mnemonic.column,
+                    message,
+                ));
+            }
+        };
+        Ok(instruction)
+    }
+}
+
+impl Statement<'_> {
+    // The number of bytes the statement is assembled into
+    fn size(&self) -> usize {
+        if self.mnemonic.text.eq_ignore_ascii_case("db") {
+            return self.operands.len();
+        }
+        let long = self.operands.get(1).is_some_and(|operand| {
+            let upper = operand.text.to_ascii_uppercase();
+            upper.starts_with("LONG") && upper[4..].starts_with(char::is_whitespace)
+        });
+        if self.mnemonic.text.eq_ignore_ascii_case("ld") && long {
+            4
+        } else {
+            2
+        }
+    }
+}
+
+// The text between two offsets of a line, without the surrounding whitespace
+fn token(line: &str, start: usize, end: usize) -> Token<'_> {
+    let text = &line[start..end];
+    let trimmed = text.trim_start();
+    Token {
+        text: trimmed.trim_end(),
+        column: start + (text.len() - trimmed.len()) + 1,
+    }
+}
+
+// The index of a register named V0 to VF
+fn register(name: &str) -> Option<u8> {
+    let digit = name.strip_prefix(['V', 'v'])?;
+    if digit.len() != 1 {
+        return None;
+    }
+    u8::from_str_radix(digit, 16).ok()
+}
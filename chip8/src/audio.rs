@@ -0,0 +1,93 @@
+use crate::{AUDIO_PATTERN_SIZE, CPU};
+
+// The XO-CHIP audio pattern is played at 4000 bits per second at the default pitch of 64
+// Each step of 48 in the pitch doubles (or halves) the rate
+const XO_BIT_RATE: f64 = 4000.0;
+const XO_DEFAULT_PITCH: f64 = 64.0;
+const XO_PITCH_PER_OCTAVE: f64 = 48.0;
+const PATTERN_BITS: usize = AUDIO_PATTERN_SIZE * 8;
+
+const DEFAULT_VOLUME: f32 = 0.25;
+const DEFAULT_TONE: f32 = 440.0;
+
+// Renders the buzzer of a CPU into samples, so that every frontend plays (or writes) the same sound
+//
+// The frontend copies the sound state of the CPU with `update` once per frame, and then asks for the samples as its
+// audio device needs them with `fill`, possibly from another thread
+// The CHIP-8 and SUPER-CHIP buzzer is a square wave of the given tone, while XO-CHIP plays its audio pattern
+pub struct Audio {
+    sample_rate: u32,
+    volume: f32,
+    tone: f32,
+    active: bool,
+    pattern: Option<[u8; AUDIO_PATTERN_SIZE]>, // Set on XO-CHIP only
+    pitch: u8,
+    phase: f64, // Position within the period of the wave, from 0 to 1
+}
+
+impl Audio {
+    // The sample rate is in Hz, such as 44100, and is at least 1
+    pub fn new(sample_rate: u32) -> Self {
+        Audio {
+            sample_rate: sample_rate.max(1),
+            volume: DEFAULT_VOLUME,
+            tone: DEFAULT_TONE,
+            active: false,
+            pattern: None,
+            pitch: XO_DEFAULT_PITCH as u8,
+            phase: 0.0,
+        }
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    // The volume goes from 0 (muted) to 1
+    pub fn set_volume(&mut self, volume: f32) {
+        self.volume = volume.clamp(0.0, 1.0);
+    }
+
+    // The frequency of the square wave played by the CHIP-8 and SUPER-CHIP programs, in Hz
+    pub fn set_tone(&mut self, tone: f32) {
+        self.tone = tone.max(1.0);
+    }
+
+    // Copy the sound timer, and on XO-CHIP the audio pattern and pitch, of the CPU
+    pub fn update(&mut self, cpu: &CPU) {
+        self.active = cpu.is_sound_active();
+        self.pattern = cpu.platform().is_xo_chip().then(|| *cpu.audio_pattern());
+        self.pitch = cpu.pitch();
+    }
+
+    // Fill the buffer with the next samples, from -volume to volume
+    // The wave carries on from one buffer to the next, and starts over once the buzzer has been silent
+    pub fn fill(&mut self, out: &mut [f32]) {
+        if !self.active {
+            out.fill(0.0);
+            self.phase = 0.0;
+            return;
+        }
+
+        // Number of periods of the wave per second
+        let frequency = match self.pattern {
+            Some(_) => {
+                let octaves = (self.pitch as f64 - XO_DEFAULT_PITCH) / XO_PITCH_PER_OCTAVE;
+                XO_BIT_RATE * 2f64.powf(octaves) / PATTERN_BITS as f64
+            }
+            None => self.tone as f64,
+        };
+        let step = frequency / self.sample_rate as f64;
+        for sample in out.iter_mut() {
+            let high = match &self.pattern {
+                Some(pattern) => {
+                    let bit = ((self.phase * PATTERN_BITS as f64) as usize).min(PATTERN_BITS - 1);
+                    pattern[bit / 8] & (0x80 >> (bit % 8)) != 0
+                }
+                None => self.phase < 0.5,
+            };
+            *sample = if high { self.volume } else { -self.volume };
+            self.phase = (self.phase + step).fract();
+        }
+    }
+}
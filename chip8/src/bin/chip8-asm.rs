@@ -0,0 +1,55 @@
+use chip8::asm::assemble;
+use chip8::Platform;
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::process;
+
+// Assembles a program into a ROM which can be run by the emulator
+fn main() {
+    let args: Vec<_> = env::args().collect();
+    /*
+        Accept 1 to 3 arguments
+        The path to the source of the program
+        The path to write the ROM to, which defaults to the source with the .ch8 extension
+        The platform the ROM is written for (chip8, schip or xochip), which defaults to chip8
+    */
+    if args.len() < 2 || args.len() > 4 {
+        eprintln!("Usage: chip8-asm path/to/source [path/to/rom] [chip8|schip|xochip]");
+        process::exit(1);
+    }
+
+    let platform = match args.get(3).map(|name| name.parse::<Platform>()) {
+        Some(Ok(platform)) => platform,
+        Some(Err(err)) => {
+            eprintln!("{}", err);
+            process::exit(1);
+        }
+        None => Platform::Chip8,
+    };
+
+    let source = match fs::read_to_string(&args[1]) {
+        Ok(source) => source,
+        Err(err) => {
+            eprintln!("Unable to read {}: {}", args[1], err);
+            process::exit(1);
+        }
+    };
+
+    let rom = match assemble(&source, platform) {
+        Ok(rom) => rom,
+        Err(err) => {
+            eprintln!("{}:{}", args[1], err);
+            process::exit(1);
+        }
+    };
+
+    let output = match args.get(2) {
+        Some(output) => Path::new(output).to_path_buf(),
+        None => Path::new(&args[1]).with_extension("ch8"),
+    };
+    if let Err(err) = fs::write(&output, rom) {
+        eprintln!("Unable to write {}: {}", output.display(), err);
+        process::exit(1);
+    }
+}
@@ -0,0 +1,99 @@
+use chip8::debugger::{Action, Debugger};
+use chip8::{Platform, CPU};
+use std::env;
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::process;
+
+const TICKS_PER_FRAME: usize = 10;
+// The program is stopped after 10 seconds at 60 FPS without reaching a breakpoint, as there is no way to interrupt it
+// from the terminal, and no key to press when it waits for one
+const MAX_TICKS: usize = 600 * TICKS_PER_FRAME;
+
+// Runs a ROM under the debugger, from the terminal
+fn main() {
+    let args: Vec<_> = env::args().collect();
+    /*
+        Accept 1 or 2 arguments
+        The path to the ROM to debug
+        The platform the ROM was written for (chip8, schip or xochip), which defaults to chip8
+    */
+    if args.len() != 2 && args.len() != 3 {
+        eprintln!("Usage: chip8-debug path/to/rom [chip8|schip|xochip]");
+        process::exit(1);
+    }
+
+    let platform = match args.get(2).map(|name| name.parse::<Platform>()) {
+        Some(Ok(platform)) => platform,
+        Some(Err(err)) => {
+            eprintln!("{}", err);
+            process::exit(1);
+        }
+        None => Platform::Chip8,
+    };
+
+    let rom = match fs::read(&args[1]) {
+        Ok(rom) => rom,
+        Err(err) => {
+            eprintln!("Unable to read {}: {}", args[1], err);
+            process::exit(1);
+        }
+    };
+
+    let mut chip8 = CPU::with_platform(platform);
+    if let Err(err) = chip8.load(&rom) {
+        eprintln!("Unable to load {}: {}", args[1], err);
+        process::exit(1);
+    }
+    let mut debugger = Debugger::new();
+    println!("Debugging {}, type help for the list of commands", args[1]);
+
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+    loop {
+        print!("(chip8) ");
+        let _ = io::stdout().flush();
+        let line = match lines.next() {
+            Some(Ok(line)) => line,
+            _ => break,
+        };
+
+        let (action, output) = debugger.command(&mut chip8, &line);
+        if !output.is_empty() {
+            println!("{}", output);
+        }
+        match action {
+            Action::Prompt => (),
+            Action::Resume => println!("{}", run(&mut chip8, &mut debugger)),
+            Action::Quit => break,
+        }
+    }
+}
+
+// Run the program until the debugger stops it, until it exits or fails, or for `MAX_TICKS` instructions
+// The timers are ticked as they would be by the frontends, once every few instructions
+fn run(chip8: &mut CPU, debugger: &mut Debugger) -> String {
+    let mut ticks = 0;
+    loop {
+        if ticks == MAX_TICKS {
+            return format!(
+                "Stopped at {:#05X} after {} instructions without reaching a breakpoint",
+                chip8.pc(),
+                MAX_TICKS
+            );
+        }
+        if let Some(reason) = debugger.check(chip8) {
+            return reason;
+        }
+        if chip8.is_halted() {
+            return "The program exited".to_string();
+        }
+        if let Err(err) = chip8.tick() {
+            return format!("Emulation stopped: {}", err);
+        }
+        ticks += 1;
+        if ticks % TICKS_PER_FRAME == 0 {
+            chip8.tick_timers();
+        }
+    }
+}
@@ -0,0 +1,38 @@
+use chip8::disasm::Disassembly;
+use chip8::Platform;
+use std::env;
+use std::fs;
+use std::process;
+
+// Prints the disassembly of a ROM
+fn main() {
+    let args: Vec<_> = env::args().collect();
+    /*
+        Accept 1 or 2 arguments
+        The path to the ROM to disassemble
+        The platform the ROM was written for (chip8, schip or xochip), which defaults to chip8
+    */
+    if args.len() != 2 && args.len() != 3 {
+        eprintln!("Usage: chip8-disasm path/to/rom [chip8|schip|xochip]");
+        process::exit(1);
+    }
+
+    let platform = match args.get(2).map(|name| name.parse::<Platform>()) {
+        Some(Ok(platform)) => platform,
+        Some(Err(err)) => {
+            eprintln!("{}", err);
+            process::exit(1);
+        }
+        None => Platform::Chip8,
+    };
+
+    let rom = match fs::read(&args[1]) {
+        Ok(rom) => rom,
+        Err(err) => {
+            eprintln!("Unable to read {}: {}", args[1], err);
+            process::exit(1);
+        }
+    };
+
+    print!("{}", Disassembly::new(&rom, platform));
+}
@@ -0,0 +1,262 @@
+use chip8::{Audio, Chip8Error, CpuState, Platform, Trace, CPU};
+use serde::Serialize;
+use std::env;
+use std::fs;
+use std::ops::Range;
+use std::process;
+
+const DEFAULT_FRAMES: u32 = 600;
+const DEFAULT_TICKS_PER_FRAME: usize = 10;
+// Characters of the pixels in the ASCII dump, indexed by the XO-CHIP planes they are set on
+const PIXELS: [char; 4] = ['.', '#', 'o', '@'];
+// The audio is written as 16-bit mono PCM, with the buzzer at its full volume
+const SAMPLE_RATE: u32 = 44100;
+const FRAMES_PER_SECOND: u32 = 60;
+
+const USAGE: &str = "Usage: chip8-headless path/to/rom [options]
+
+Runs a ROM without a window for a fixed number of frames, then prints the screen as ASCII art and the registers as JSON
+
+Options:
+    --frames N        Number of frames to run (default 600, 10 seconds at 60 FPS)
+    --ticks N         Number of instructions run per frame (default 10)
+    --platform NAME   chip8, schip or xochip (default chip8)
+    --seed N          Seed of the random number generator (default 0)
+    --keys PATH       Script of the key presses, one `FRAME KEY down|up` per line, such as `120 5 down`
+    --screen PATH     Write the screen to a file instead of printing it
+    --regs PATH       Write the registers to a file instead of printing them
+    --trace PATH      Write a trace of every instruction run to a file, one line per instruction
+    --wav PATH        Write the sound of the buzzer to a WAV file
+    --trace-range A-B Only trace the instructions from address A up to B excluded, such as `0x200-0x300`";
+
+// A key press of the script, sent at the start of the given frame
+struct ScriptedKey {
+    frame: u32,
+    key: usize,
+    pressed: bool,
+}
+
+// Runs a ROM without any window, so that its behaviour can be checked by scripts
+fn main() {
+    let mut args = env::args().skip(1);
+    let mut rom_path = None;
+    let mut frames = DEFAULT_FRAMES;
+    let mut ticks = DEFAULT_TICKS_PER_FRAME;
+    let mut platform = Platform::Chip8;
+    let mut seed = 0;
+    let mut keys_path = None;
+    let mut screen_path = None;
+    let mut regs_path = None;
+    let mut trace_path = None;
+    let mut trace_range = None;
+    let mut wav_path = None;
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().unwrap_or_else(|| fail(USAGE));
+        match arg.as_str() {
+            "--frames" => frames = value().parse().unwrap_or_else(|_| fail("Invalid --frames")),
+            "--ticks" => ticks = value().parse().unwrap_or_else(|_| fail("Invalid --ticks")),
+            "--platform" => platform = value().parse().unwrap_or_else(|err: String| fail(&err)),
+            "--seed" => seed = value().parse().unwrap_or_else(|_| fail("Invalid --seed")),
+            "--keys" => keys_path = Some(value()),
+            "--screen" => screen_path = Some(value()),
+            "--regs" => regs_path = Some(value()),
+            "--trace" => trace_path = Some(value()),
+            "--wav" => wav_path = Some(value()),
+            "--trace-range" => {
+                trace_range =
+                    Some(parse_range(&value()).unwrap_or_else(|| fail("Invalid --trace-range")))
+            }
+            "--help" | "-h" => {
+                println!("{}", USAGE);
+                return;
+            }
+            _ if rom_path.is_none() && !arg.starts_with("--") => rom_path = Some(arg),
+            _ => fail(USAGE),
+        }
+    }
+    let rom_path = rom_path.unwrap_or_else(|| fail(USAGE));
+
+    let rom = fs::read(&rom_path)
+        .unwrap_or_else(|err| fail(&format!("Unable to read {}: {}", rom_path, err)));
+    let script = match &keys_path {
+        Some(path) => {
+            let text = fs::read_to_string(path)
+                .unwrap_or_else(|err| fail(&format!("Unable to read {}: {}", path, err)));
+            parse_script(&text).unwrap_or_else(|err| fail(&format!("{}:{}", path, err)))
+        }
+        None => Vec::new(),
+    };
+
+    // Create the emulator
+    let mut chip8 = CPU::with_platform(platform);
+    chip8.set_seed(seed);
+    chip8
+        .load(&rom)
+        .unwrap_or_else(|err| fail(&format!("Unable to load {}: {}", rom_path, err)));
+    if let Some(path) = &trace_path {
+        let trace = Trace::to_file(path)
+            .unwrap_or_else(|err| fail(&format!("Unable to write {}: {}", path, err)));
+        chip8.set_trace(match trace_range {
+            Some(range) => trace.with_filter(range),
+            None => trace,
+        });
+    }
+
+    // Run the frames, stopping early if the program exits or fails
+    // The sound of each frame is rendered once its instructions are run, before the sound timer goes down
+    let mut audio = Audio::new(SAMPLE_RATE);
+    audio.set_volume(1.0);
+    let mut samples = Vec::new();
+    let mut frame_samples = [0.0; (SAMPLE_RATE / FRAMES_PER_SECOND) as usize];
+    let mut error = None;
+    let mut frame = 0;
+    'frames: while frame < frames && !chip8.is_halted() {
+        for key in script.iter().filter(|key| key.frame == frame) {
+            chip8.keypress(key.key, key.pressed);
+        }
+        for _ in 0..ticks {
+            if let Err(err) = chip8.tick() {
+                error = Some(err);
+                break 'frames;
+            }
+        }
+        if wav_path.is_some() {
+            audio.update(&chip8);
+            audio.fill(&mut frame_samples);
+            samples.extend_from_slice(&frame_samples);
+        }
+        chip8.tick_timers();
+        frame += 1;
+    }
+
+    output(&screen_path, &screen(&chip8));
+    output(&regs_path, &registers(&chip8, frame, error));
+    if let (Some(path), Some(mut trace)) = (&trace_path, chip8.take_trace()) {
+        if let Err(err) = trace.flush() {
+            fail(&format!("Unable to write {}: {}", path, err));
+        }
+    }
+    if let Some(path) = &wav_path {
+        if let Err(err) = fs::write(path, wav(&samples)) {
+            fail(&format!("Unable to write {}: {}", path, err));
+        }
+    }
+    if let Some(err) = error {
+        eprintln!("Emulation stopped: {}", err);
+        process::exit(2);
+    }
+}
+
+fn fail(message: &str) -> ! {
+    eprintln!("{}", message);
+    process::exit(1);
+}
+
+fn output(path: &Option<String>, text: &str) {
+    match path {
+        Some(path) => {
+            if let Err(err) = fs::write(path, text) {
+                fail(&format!("Unable to write {}: {}", path, err));
+            }
+        }
+        None => print!("{}", text),
+    }
+}
+
+// Parse a range of addresses such as `0x200-0x300`
+fn parse_range(text: &str) -> Option<Range<u16>> {
+    let address = |text: &str| u16::from_str_radix(text.trim().trim_start_matches("0x"), 16).ok();
+    let (start, end) = text.split_once('-')?;
+    Some(address(start)?..address(end)?)
+}
+
+// Parse the script of the key presses
+// Empty lines and anything after a # are ignored
+fn parse_script(text: &str) -> Result<Vec<ScriptedKey>, String> {
+    let mut script = Vec::new();
+    for (idx, line) in text.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or_default();
+        let fields: Vec<_> = line.split_whitespace().collect();
+        if fields.is_empty() {
+            continue;
+        }
+        let key = match fields.as_slice() {
+            [frame, key, state] => {
+                let frame = frame.parse().ok();
+                let key = usize::from_str_radix(key.trim_start_matches("0x"), 16)
+                    .ok()
+                    .filter(|&key| key < 16);
+                let pressed = match *state {
+                    "down" => Some(true),
+                    "up" => Some(false),
+                    _ => None,
+                };
+                frame.zip(key).zip(pressed)
+            }
+            _ => None,
+        };
+        match key {
+            Some(((frame, key), pressed)) => script.push(ScriptedKey {
+                frame,
+                key,
+                pressed,
+            }),
+            None => return Err(format!("{}: expected `FRAME KEY down|up`", idx + 1)),
+        }
+    }
+    Ok(script)
+}
+
+// The screen as ASCII art, one line per row of pixels
+fn screen(chip8: &CPU) -> String {
+    let display = chip8.get_display();
+    let mut text = String::with_capacity((display.width + 1) * display.height);
+    for row in display.pixels.chunks(display.width) {
+        text.extend(row.iter().map(|&pixel| PIXELS[pixel as usize]));
+        text.push('\n');
+    }
+    text
+}
+
+// The samples as a WAV file, in 16-bit mono PCM
+fn wav(samples: &[f32]) -> Vec<u8> {
+    let data_len = samples.len() as u32 * 2;
+    let mut wav = Vec::with_capacity(44 + data_len as usize);
+    wav.extend_from_slice(b"RIFF");
+    wav.extend_from_slice(&(36 + data_len).to_le_bytes());
+    wav.extend_from_slice(b"WAVEfmt ");
+    wav.extend_from_slice(&16u32.to_le_bytes()); // Size of the format chunk
+    wav.extend_from_slice(&1u16.to_le_bytes()); // PCM
+    wav.extend_from_slice(&1u16.to_le_bytes()); // Mono
+    wav.extend_from_slice(&SAMPLE_RATE.to_le_bytes());
+    wav.extend_from_slice(&(SAMPLE_RATE * 2).to_le_bytes()); // Bytes per second
+    wav.extend_from_slice(&2u16.to_le_bytes()); // Bytes per sample
+    wav.extend_from_slice(&16u16.to_le_bytes()); // Bits per sample
+    wav.extend_from_slice(b"data");
+    wav.extend_from_slice(&data_len.to_le_bytes());
+    for sample in samples {
+        wav.extend_from_slice(&((sample * i16::MAX as f32) as i16).to_le_bytes());
+    }
+    wav
+}
+
+// The registers as a JSON object, along with the number of frames run and the error which stopped the emulation
+#[derive(Serialize)]
+struct Registers {
+    frames: u32,
+    #[serde(flatten)]
+    state: CpuState,
+    error: Option<String>,
+}
+
+fn registers(chip8: &CPU, frames: u32, error: Option<Chip8Error>) -> String {
+    let registers = Registers {
+        frames,
+        state: chip8.cpu_state(),
+        error: error.map(|err| err.to_string()),
+    };
+    let mut json =
+        serde_json::to_string_pretty(&registers).expect("the registers are plain values");
+    json.push('\n');
+    json
+}
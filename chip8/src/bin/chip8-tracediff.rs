@@ -0,0 +1,107 @@
+use chip8::tracediff::{diff_cpus, diff_traces};
+use chip8::{Platform, Quirks, CPU};
+use std::env;
+use std::fs;
+use std::process;
+
+const DEFAULT_CONTEXT: usize = 5;
+const DEFAULT_TICKS: usize = 100_000;
+const TICKS_PER_FRAME: usize = 10;
+
+const USAGE: &str = "Usage: chip8-tracediff path/to/trace path/to/other/trace [--context N]
+       chip8-tracediff --rom path/to/rom --quirks A B [options]
+
+Compares two traces written by `chip8-headless --trace` (or by another emulator, in the same format), or two runs
+of a ROM with different quirks, and prints the first instruction at which they differ along with the lines around it
+Exits with 0 when there is no difference, 1 when there is one, and 2 when the comparison couldn't be made
+
+Traces are only compared on the address, the OP Code and the registers of their lines, so a difference in the RAM or
+on the screen shows up once it changes a register, if it ever does
+The runs of a ROM are also compared on the whole RAM and screen after every instruction
+
+Options:
+    --context N       Number of lines printed before and after the divergence (default 5)
+    --rom PATH        ROM to run twice, instead of reading traces
+    --quirks A B      Quirks of each run: vip, chip48, schip, xochip or modern
+    --platform NAME   chip8, schip or xochip (default chip8)
+    --ticks N         Number of instructions to compare (default 100000)
+    --seed N          Seed of the random number generator of both runs (default 0)";
+
+// Compares two runs of a program, to validate the emulation against another emulator or between quirks
+fn main() {
+    let mut args = env::args().skip(1);
+    let mut paths = Vec::new();
+    let mut context = DEFAULT_CONTEXT;
+    let mut rom_path = None;
+    let mut quirks = None;
+    let mut platform = Platform::Chip8;
+    let mut ticks = DEFAULT_TICKS;
+    let mut seed = 0;
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().unwrap_or_else(|| fail(USAGE));
+        match arg.as_str() {
+            "--context" => {
+                context = value()
+                    .parse()
+                    .unwrap_or_else(|_| fail("Invalid --context"))
+            }
+            "--rom" => rom_path = Some(value()),
+            "--quirks" => {
+                let a: Quirks = value().parse().unwrap_or_else(|err: String| fail(&err));
+                let b: Quirks = value().parse().unwrap_or_else(|err: String| fail(&err));
+                quirks = Some((a, b));
+            }
+            "--platform" => platform = value().parse().unwrap_or_else(|err: String| fail(&err)),
+            "--ticks" => ticks = value().parse().unwrap_or_else(|_| fail("Invalid --ticks")),
+            "--seed" => seed = value().parse().unwrap_or_else(|_| fail("Invalid --seed")),
+            "--help" | "-h" => {
+                println!("{}", USAGE);
+                return;
+            }
+            _ if !arg.starts_with("--") => paths.push(arg),
+            _ => fail(USAGE),
+        }
+    }
+
+    let divergence = match (rom_path, quirks, paths.as_slice()) {
+        (Some(path), Some((quirks_a, quirks_b)), []) => {
+            let rom = read(&path);
+            let mut cpus = [quirks_a, quirks_b].map(|quirks| {
+                let mut cpu = CPU::with_platform(platform);
+                cpu.set_quirks(quirks);
+                cpu.set_seed(seed);
+                cpu.load(&rom)
+                    .unwrap_or_else(|err| fail(&format!("Unable to load {}: {}", path, err)));
+                cpu
+            });
+            let [a, b] = &mut cpus;
+            diff_cpus(a, b, ticks, TICKS_PER_FRAME, context)
+        }
+        (None, None, [path_a, path_b]) => {
+            let (a, b) = (read(path_a), read(path_b));
+            diff_traces(
+                &String::from_utf8_lossy(&a),
+                &String::from_utf8_lossy(&b),
+                context,
+            )
+        }
+        _ => fail(USAGE),
+    };
+
+    match divergence {
+        Some(divergence) => {
+            print!("{}", divergence);
+            process::exit(1);
+        }
+        None => println!("No difference"),
+    }
+}
+
+fn fail(message: &str) -> ! {
+    eprintln!("{}", message);
+    process::exit(2);
+}
+
+fn read(path: &str) -> Vec<u8> {
+    fs::read(path).unwrap_or_else(|err| fail(&format!("Unable to read {}: {}", path, err)))
+}
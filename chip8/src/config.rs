@@ -0,0 +1,241 @@
+use crate::{database, Keymap, Platform, Quirks};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+// Settings of the frontends, from a TOML file such as
+//
+//     [defaults]
+//     ips = 700
+//
+//     [roms.f100197f0f2f05b4f3c8c31ab9c2c3930d3e9571]
+//     name = "INVADERS"
+//     quirks = "vip"
+//     foreground = "FFB000"
+//
+//     [roms.f100197f0f2f05b4f3c8c31ab9c2c3930d3e9571.keys]
+//     ArrowLeft = 4
+//     ArrowRight = 6
+//
+// The ROMs are keyed by the SHA-1 of their bytes, in hexadecimal, so that renaming or moving them keeps their settings
+// Every setting is optional: the ones of a ROM override the ones of the ROM database (see database.rs), which override
+// the defaults, which override the ones of the frontend
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub defaults: Settings,
+    pub roms: BTreeMap<String, Settings>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Settings {
+    // The name of the ROM, for the people reading the file only
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    // chip8, schip or xochip
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub platform: Option<String>,
+    // The name of a preset, such as vip or schip
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub quirks: Option<String>,
+    // Instructions run per second
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ips: Option<u32>,
+    // Size of the lo-res pixels, on the desktop only
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scale: Option<u32>,
+    // Colors of the pixels which are on and off, such as FFFFFF
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub foreground: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub background: Option<String>,
+    // Volume of the buzzer, from 0 to 100, and pitch in Hz
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub volume: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pitch: Option<f32>,
+    // The name of a keymap preset, keypad or numpad
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub keymap: Option<String>,
+    // Keys bound on top of the ones of the preset, such as `ArrowLeft = 4`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub keys: Option<Keymap>,
+    // Buttons of the gamepads bound to CHIP-8 keys, named as by SDL (such as `dpleft`, `a` or `leftshoulder`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub buttons: Option<Keymap>,
+}
+
+impl Config {
+    // Parse and check a TOML file, so that every setting of it can be used as is
+    pub fn parse(text: &str) -> Result<Config, String> {
+        let mut config: Config = toml::from_str(text).map_err(|err| err.to_string())?;
+        config
+            .defaults
+            .check()
+            .map_err(|err| format!("defaults: {}", err))?;
+        for (key, settings) in &config.roms {
+            if key.len() != 40 || !key.chars().all(|c| c.is_ascii_hexdigit()) {
+                return Err(format!("{}: not the SHA-1 of a ROM", key));
+            }
+            settings
+                .check()
+                .map_err(|err| format!("{}: {}", key, err))?;
+        }
+        // The hashes are written in lowercase, but may have been typed in uppercase
+        config.roms = config
+            .roms
+            .into_iter()
+            .map(|(key, settings)| (key.to_ascii_lowercase(), settings))
+            .collect();
+        Ok(config)
+    }
+
+    pub fn to_toml(&self) -> String {
+        toml::to_string(self).expect("the settings are plain values")
+    }
+
+    // The settings of a ROM, given the SHA-1 returned by `CPU::rom_hash`, on top of the ones of the ROM database and
+    // the defaults
+    pub fn settings(&self, rom_hash: &[u8; 20]) -> Settings {
+        let mut settings = self.defaults.clone();
+        if let Some(info) = database::lookup(rom_hash) {
+            settings = settings.merge(&info.settings);
+        }
+        match self.roms.get(&rom_key(rom_hash)) {
+            Some(overrides) => settings.merge(overrides),
+            None => settings,
+        }
+    }
+
+    // Remember the settings changed for a ROM, on top of the ones it already had
+    pub fn update(&mut self, rom_hash: &[u8; 20], changes: &Settings) {
+        let settings = self.roms.entry(rom_key(rom_hash)).or_default();
+        *settings = settings.merge(changes);
+    }
+}
+
+impl Settings {
+    // The settings of `self`, replaced by the ones set in `overrides`
+    pub fn merge(&self, overrides: &Settings) -> Settings {
+        Settings {
+            name: overrides.name.clone().or_else(|| self.name.clone()),
+            platform: overrides.platform.clone().or_else(|| self.platform.clone()),
+            quirks: overrides.quirks.clone().or_else(|| self.quirks.clone()),
+            ips: overrides.ips.or(self.ips),
+            scale: overrides.scale.or(self.scale),
+            foreground: overrides
+                .foreground
+                .clone()
+                .or_else(|| self.foreground.clone()),
+            background: overrides
+                .background
+                .clone()
+                .or_else(|| self.background.clone()),
+            volume: overrides.volume.or(self.volume),
+            pitch: overrides.pitch.or(self.pitch),
+            keymap: overrides.keymap.clone().or_else(|| self.keymap.clone()),
+            // The keys and buttons are bound on top of each other, rather than replaced altogether
+            keys: merge_keymaps(&self.keys, &overrides.keys),
+            buttons: merge_keymaps(&self.buttons, &overrides.buttons),
+        }
+    }
+
+    // Whether all the settings which are set have valid values
+    pub fn check(&self) -> Result<(), String> {
+        if let Some(platform) = &self.platform {
+            platform.parse::<Platform>()?;
+        }
+        if let Some(quirks) = &self.quirks {
+            quirks.parse::<Quirks>()?;
+        }
+        if let Some(keymap) = &self.keymap {
+            keymap.parse::<Keymap>()?;
+        }
+        for keymap in [&self.keys, &self.buttons].into_iter().flatten() {
+            keymap.check()?;
+        }
+        for color in [&self.foreground, &self.background].into_iter().flatten() {
+            parse_color(color)?;
+        }
+        if self.ips == Some(0) || self.scale == Some(0) {
+            return Err("ips and scale must be at least 1".to_string());
+        }
+        if self.volume.is_some_and(|volume| volume > 100) {
+            return Err("volume must be from 0 to 100".to_string());
+        }
+        if self.pitch.is_some_and(|pitch| pitch <= 0.0) {
+            return Err("pitch must be above 0".to_string());
+        }
+        Ok(())
+    }
+
+    // The settings are valid once checked, so the ones which can't be parsed are treated as unset
+    pub fn platform(&self) -> Option<Platform> {
+        self.platform
+            .as_ref()
+            .and_then(|platform| platform.parse().ok())
+    }
+
+    pub fn quirks(&self) -> Option<Quirks> {
+        self.quirks.as_ref().and_then(|quirks| quirks.parse().ok())
+    }
+
+    // The keys of the preset, or of the default one, along with the keys bound on top of them
+    pub fn keymap(&self) -> Keymap {
+        let mut keymap: Keymap = self
+            .keymap
+            .as_ref()
+            .and_then(|keymap| keymap.parse().ok())
+            .unwrap_or_default();
+        if let Some(keys) = &self.keys {
+            keymap.extend(keys);
+        }
+        keymap
+    }
+
+    pub fn foreground(&self) -> Option<[u8; 3]> {
+        self.foreground
+            .as_ref()
+            .and_then(|color| parse_color(color).ok())
+    }
+
+    pub fn background(&self) -> Option<[u8; 3]> {
+        self.background
+            .as_ref()
+            .and_then(|color| parse_color(color).ok())
+    }
+}
+
+fn merge_keymaps(keymap: &Option<Keymap>, overrides: &Option<Keymap>) -> Option<Keymap> {
+    match (keymap, overrides) {
+        (Some(keymap), Some(overrides)) => {
+            let mut keymap = keymap.clone();
+            keymap.extend(overrides);
+            Some(keymap)
+        }
+        (keymap, overrides) => overrides.clone().or_else(|| keymap.clone()),
+    }
+}
+
+// A color such as `FF8800` or `#FF8800`, as red, green and blue
+pub fn parse_color(text: &str) -> Result<[u8; 3], String> {
+    let hex = text.trim_start_matches('#');
+    match u32::from_str_radix(hex, 16) {
+        Ok(rgb) if hex.len() == 6 => Ok([(rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8]),
+        _ => Err(format!("invalid color: {}", text)),
+    }
+}
+
+// The key of a ROM in the file, which is its SHA-1 in hexadecimal
+pub fn rom_key(rom_hash: &[u8; 20]) -> String {
+    rom_hash
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+// The SHA-1 of a ROM, as returned by `CPU::rom_hash` once it is loaded
+// The frontends need it before loading the ROM, as the platform of the CPU must be set first
+pub fn hash_rom(rom: &[u8]) -> [u8; 20] {
+    sha1_smol::Sha1::from(rom).digest().bytes()
+}
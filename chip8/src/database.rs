@@ -0,0 +1,126 @@
+use crate::config::{self, Settings};
+use crate::Platform;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::sync::OnceLock;
+
+// Metadata of the known ROMs, in the format of the programs.json file of the CHIP-8 database maintained by the
+// community (https://github.com/chip-8/chip-8-database)
+// Only the ROMs bundled with the emulator are listed, with what was checked on them: their title screen, the keys
+// their code reads, and the platform and speed the golden tests run them at (see tests/roms.rs)
+// They run the same with the CHIP-48 quirks as with the modern ones, but not with the display wait of the COSMAC VIP
+// The programs.json file of the database can be dropped in its place to know every ROM, as the fields which aren't
+// used here are ignored
+const DATABASE: &str = include_str!("database.json");
+
+// The platforms of the database, with the platform and quirks preset of the emulator which run their programs
+// The ones which aren't listed, such as CHIP-8X or MEGA-CHIP, can't be emulated
+const PLATFORMS: [(&str, Platform, &str); 7] = [
+    ("originalChip8", Platform::Chip8, "vip"),
+    ("hybridVIP", Platform::Chip8, "vip"),
+    ("modernChip8", Platform::Chip8, "modern"),
+    ("chip48", Platform::Chip8, "chip48"),
+    ("superchip1", Platform::SuperChip, "schip"),
+    ("superchip", Platform::SuperChip, "schip"),
+    ("xochip", Platform::XoChip, "xochip"),
+];
+
+// The controls are listed in this order, and the other ones after them
+const CONTROLS: [&str; 6] = ["up", "down", "left", "right", "a", "b"];
+
+#[derive(Deserialize)]
+struct Program {
+    title: String,
+    description: Option<String>,
+    #[serde(default)]
+    authors: Vec<String>,
+    roms: BTreeMap<String, Rom>,
+}
+
+#[derive(Deserialize)]
+struct Rom {
+    #[serde(default)]
+    platforms: Vec<String>,
+    tickrate: Option<u32>, // Instructions per frame
+    colors: Option<Colors>,
+    #[serde(default)]
+    keys: BTreeMap<String, u8>,
+}
+
+#[derive(Deserialize)]
+struct Colors {
+    #[serde(default)]
+    pixels: Vec<String>, // Background first, then the colors of the planes
+}
+
+// What is known about a ROM
+#[derive(Debug, Clone, PartialEq)]
+pub struct RomInfo {
+    pub title: String,
+    pub description: Option<String>,
+    pub authors: Vec<String>,
+    // The platform, quirks, speed and colors the ROM runs best with
+    pub settings: Settings,
+    // The CHIP-8 key of each control, such as `left` or `a`
+    pub keys: Vec<(String, usize)>,
+}
+
+impl RomInfo {
+    // The controls as a single line, such as `left 4, right 6, a 5`
+    pub fn controls(&self) -> String {
+        let keys: Vec<_> = self
+            .keys
+            .iter()
+            .map(|(control, key)| format!("{} {:X}", control, key))
+            .collect();
+        keys.join(", ")
+    }
+}
+
+// The metadata of a ROM, given the SHA-1 returned by `CPU::rom_hash`
+pub fn lookup(rom_hash: &[u8; 20]) -> Option<RomInfo> {
+    static PROGRAMS: OnceLock<Vec<Program>> = OnceLock::new();
+    let programs = PROGRAMS
+        .get_or_init(|| serde_json::from_str(DATABASE).expect("the bundled database is valid"));
+
+    let key = config::rom_key(rom_hash);
+    programs.iter().find_map(|program| {
+        let rom = program.roms.get(&key)?;
+        let mut settings = Settings::default();
+        // The first platform that can be emulated is the one the ROM was written for
+        if let Some((_, platform, quirks)) = rom
+            .platforms
+            .iter()
+            .find_map(|name| PLATFORMS.iter().find(|(id, ..)| id == name))
+        {
+            settings.platform = Some(platform.to_string());
+            settings.quirks = Some(quirks.to_string());
+        }
+        settings.ips = rom.tickrate.map(|tickrate| tickrate * 60);
+        if let Some(colors) = &rom.colors {
+            let colors = &colors.pixels;
+            let valid = |color: &&String| config::parse_color(color).is_ok();
+            settings.background = colors.first().filter(valid).cloned();
+            settings.foreground = colors.get(1).filter(valid).cloned();
+        }
+
+        let mut keys: Vec<_> = rom
+            .keys
+            .iter()
+            .filter(|(_, &key)| key < 16)
+            .map(|(control, &key)| (control.clone(), key as usize))
+            .collect();
+        keys.sort_by_key(|(control, _)| {
+            let position = CONTROLS.iter().position(|name| name == control);
+            (position.unwrap_or(CONTROLS.len()), control.clone())
+        });
+
+        Some(RomInfo {
+            title: program.title.clone(),
+            description: program.description.clone(),
+            authors: program.authors.clone(),
+            settings,
+            keys,
+        })
+    })
+}
@@ -0,0 +1,509 @@
+use crate::{Instruction, WatchAction, WatchTarget, CPU};
+use std::collections::BTreeSet;
+use std::fmt::Write;
+use std::ops::Range;
+
+const HELP: &str = "\
+break [ADDR]        Stop when the PC reaches ADDR, or list the breakpoints
+delete ADDR         Remove the breakpoint at ADDR
+step [N]            Run N instructions (1 by default)
+next                Run one instruction, running whole subroutines on CALL
+continue            Run until a breakpoint or a watch stops the program
+regs                Print the registers
+mem ADDR [LEN]      Print LEN bytes of the RAM (16 by default) from ADDR
+stack               Print the return addresses on the stack
+watch [REG]         Stop when the register (Vx, I, DT or ST) changes, or list the watches
+watch ADDR [LEN]    Stop when the program writes to LEN bytes of the RAM (1 by default) from ADDR
+rwatch ADDR [LEN]   Stop when the program reads from LEN bytes of the RAM (1 by default) from ADDR
+unwatch REG|ADDR    Remove the watch on the register, or the watches starting at ADDR
+disasm [ADDR] [N]   Disassemble N instructions (10 by default) from ADDR (the PC by default)
+set REG VALUE       Set a register (Vx, I, PC, DT or ST)
+help                Print this help
+quit                Stop debugging
+
+The addresses and the values can be numbers (decimal, or hexadecimal with 0x), registers (pc, i or Vx), or their sum (i+2)";
+
+// What the frontend should do once a command is done
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    // Wait for the next command
+    Prompt,
+    // Run the program, calling `Debugger::check` before every instruction
+    Resume,
+    // Stop debugging, and the emulation along with it
+    Quit,
+}
+
+// A register which can be watched or set
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Register {
+    V(usize),
+    I,
+    Pc,
+    Dt,
+    St,
+}
+
+impl Register {
+    fn parse(name: &str) -> Option<Register> {
+        let name = name.to_ascii_uppercase();
+        match name.as_str() {
+            "I" => Some(Register::I),
+            "PC" => Some(Register::Pc),
+            "DT" => Some(Register::Dt),
+            "ST" => Some(Register::St),
+            _ => {
+                let digit = name.strip_prefix('V').filter(|digit| digit.len() == 1)?;
+                usize::from_str_radix(digit, 16).ok().map(Register::V)
+            }
+        }
+    }
+
+    fn get(&self, cpu: &CPU) -> u16 {
+        match *self {
+            Register::V(x) => cpu.v_reg()[x] as u16,
+            Register::I => cpu.i_reg(),
+            Register::Pc => cpu.pc(),
+            Register::Dt => cpu.dt() as u16,
+            Register::St => cpu.st() as u16,
+        }
+    }
+
+    fn name(&self) -> String {
+        match *self {
+            Register::V(x) => format!("V{:X}", x),
+            Register::I => "I".to_string(),
+            Register::Pc => "PC".to_string(),
+            Register::Dt => "DT".to_string(),
+            Register::St => "ST".to_string(),
+        }
+    }
+}
+
+// A register being watched, along with the last value it was seen with
+struct Watch {
+    register: Register,
+    value: u16,
+}
+
+// A range of the RAM being watched, through a watchpoint of the CPU
+struct MemoryWatch {
+    id: usize,
+    range: Range<usize>,
+    read: bool,
+}
+
+// A gdb-like debugger, driven by text commands such as `break 0x2A4`, `step` or `mem I 16`
+//
+// The debugger doesn't run the program on its own when resumed, so that the frontends can keep the timers,
+// the keys and the screen going: they run the program as usual, but call `check` before every instruction
+pub struct Debugger {
+    breakpoints: BTreeSet<u16>,
+    watches: Vec<Watch>,
+    memory_watches: Vec<MemoryWatch>,
+    // Set by `next` on a CALL, to stop once the subroutine returns (to the address, with the stack pointer)
+    step_over: Option<(u16, u16)>,
+    // The last command, which an empty line repeats
+    last_command: String,
+}
+
+impl Default for Debugger {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Debugger {
+    pub fn new() -> Self {
+        Debugger {
+            breakpoints: BTreeSet::new(),
+            watches: Vec::new(),
+            memory_watches: Vec::new(),
+            step_over: None,
+            last_command: String::new(),
+        }
+    }
+
+    // Called before every instruction while the program runs after `Action::Resume`
+    // Returns the reason to stop, if the program must be stopped before running the instruction at the PC
+    pub fn check(&mut self, cpu: &mut CPU) -> Option<String> {
+        let mut reasons = Vec::new();
+        if let Some(hit) = cpu.take_watch_hit() {
+            reasons.push(hit.to_string());
+        } else if let Some((addr, sp)) = self.step_over {
+            if cpu.pc() == addr && cpu.sp() == sp {
+                self.step_over = None;
+                return Some(format!("{:#05X}: {}", addr, self.instruction_at(cpu, addr)));
+            }
+        }
+
+        for watch in self.watches.iter_mut() {
+            let value = watch.register.get(cpu);
+            if value != watch.value {
+                reasons.push(format!(
+                    "Watch {}: {:#04X} -> {:#04X}",
+                    watch.register.name(),
+                    watch.value,
+                    value
+                ));
+                watch.value = value;
+            }
+        }
+        if self.breakpoints.contains(&cpu.pc()) {
+            reasons.push(format!("Breakpoint at {:#05X}", cpu.pc()));
+        }
+        if reasons.is_empty() {
+            return None;
+        }
+
+        // Running `next` is cut short by the other reasons to stop
+        self.step_over = None;
+        let pc = cpu.pc();
+        reasons.push(format!("{:#05X}: {}", pc, self.instruction_at(cpu, pc)));
+        Some(reasons.join("\n"))
+    }
+
+    // Run a command, and return the action the frontend should take along with the text to print
+    pub fn command(&mut self, cpu: &mut CPU, line: &str) -> (Action, String) {
+        let line = match line.trim() {
+            "" => self.last_command.clone(),
+            line => line.to_string(),
+        };
+        self.last_command = line.clone();
+
+        let words: Vec<_> = line.split_whitespace().collect();
+        let (command, args) = match words.split_first() {
+            Some((command, args)) => (command.to_ascii_lowercase(), args),
+            None => return (Action::Prompt, String::new()),
+        };
+        let result = match (command.as_str(), args) {
+            ("break" | "b", []) => Ok(self.list_breakpoints()),
+            ("break" | "b", [addr]) => self.add_breakpoint(cpu, addr),
+            ("delete" | "d", [addr]) => self.delete_breakpoint(cpu, addr),
+            ("step" | "s", []) => self.step(cpu, "1"),
+            ("step" | "s", [count]) => self.step(cpu, count),
+            ("next" | "n", []) => return self.next(cpu),
+            ("continue" | "c", []) => return self.resume(cpu),
+            ("regs" | "r", []) => Ok(regs(cpu)),
+            ("mem" | "m", [addr]) => mem(cpu, addr, "16"),
+            ("mem" | "m", [addr, len]) => mem(cpu, addr, len),
+            ("stack", []) => Ok(stack(cpu)),
+            ("watch" | "w", []) => Ok(self.list_watches()),
+            ("watch" | "w", [target]) => self.watch(cpu, target),
+            ("watch" | "w", [addr, len]) => self.watch_memory(cpu, addr, len, false),
+            ("rwatch", [addr]) => self.watch_memory(cpu, addr, "1", true),
+            ("rwatch", [addr, len]) => self.watch_memory(cpu, addr, len, true),
+            ("unwatch", [target]) => self.unwatch(cpu, target),
+            ("disasm" | "x", []) => self.disasm(cpu, "pc", "10"),
+            ("disasm" | "x", [addr]) => self.disasm(cpu, addr, "10"),
+            ("disasm" | "x", [addr, count]) => self.disasm(cpu, addr, count),
+            ("set", [register, value]) => self.set(cpu, register, value),
+            ("help" | "h", _) => Ok(HELP.to_string()),
+            ("quit" | "q", []) => return (Action::Quit, String::new()),
+            _ => Err(format!("Invalid command: {} (try help)", line)),
+        };
+        match result {
+            Ok(output) => (Action::Prompt, output),
+            Err(err) => (Action::Prompt, err),
+        }
+    }
+
+    // BREAKPOINTS
+    fn list_breakpoints(&self) -> String {
+        if self.breakpoints.is_empty() {
+            return "No breakpoints".to_string();
+        }
+        let addrs: Vec<_> = self
+            .breakpoints
+            .iter()
+            .map(|addr| format!("{:#05X}", addr))
+            .collect();
+        format!("Breakpoints: {}", addrs.join(" "))
+    }
+
+    fn add_breakpoint(&mut self, cpu: &CPU, addr: &str) -> Result<String, String> {
+        let addr = value(cpu, addr)?;
+        self.breakpoints.insert(addr);
+        Ok(format!("Breakpoint at {:#05X}", addr))
+    }
+
+    fn delete_breakpoint(&mut self, cpu: &CPU, addr: &str) -> Result<String, String> {
+        let addr = value(cpu, addr)?;
+        if !self.breakpoints.remove(&addr) {
+            return Err(format!("No breakpoint at {:#05X}", addr));
+        }
+        Ok(format!("Deleted the breakpoint at {:#05X}", addr))
+    }
+
+    // EXECUTION
+    fn step(&mut self, cpu: &mut CPU, count: &str) -> Result<String, String> {
+        let count = value(cpu, count)?;
+        let mut output = String::new();
+        for _ in 0..count {
+            cpu.tick()
+                .map_err(|err| format!("Emulation stopped: {}", err))?;
+            self.update_watches(cpu);
+            // The memory watches stop the steps short, as they would stop the program
+            if let Some(hit) = cpu.take_watch_hit() {
+                output = format!("{}\n", hit);
+                break;
+            }
+        }
+        let pc = cpu.pc();
+        let _ = write!(output, "{:#05X}: {}", pc, self.instruction_at(cpu, pc));
+        Ok(output)
+    }
+
+    // Like `step`, except that a CALL is run until the subroutine returns
+    fn next(&mut self, cpu: &mut CPU) -> (Action, String) {
+        let pc = cpu.pc();
+        match self.decode_at(cpu, pc) {
+            Some(Instruction::Call(_)) => {
+                self.step_over = Some((pc.wrapping_add(2), cpu.sp()));
+                self.resume(cpu)
+            }
+            _ => match self.step(cpu, "1") {
+                Ok(output) | Err(output) => (Action::Prompt, output),
+            },
+        }
+    }
+
+    // The instruction at the PC is run right away, so that the program doesn't stop again at the breakpoint it is on
+    fn resume(&mut self, cpu: &mut CPU) -> (Action, String) {
+        if let Err(err) = cpu.tick() {
+            self.step_over = None;
+            return (Action::Prompt, format!("Emulation stopped: {}", err));
+        }
+        match self.check(cpu) {
+            Some(reason) => (Action::Prompt, reason),
+            None => (Action::Resume, String::new()),
+        }
+    }
+
+    // WATCHES
+    fn list_watches(&self) -> String {
+        if self.watches.is_empty() && self.memory_watches.is_empty() {
+            return "No watches".to_string();
+        }
+        let registers = self.watches.iter().map(|watch| watch.register.name());
+        let memory = self.memory_watches.iter().map(|watch| {
+            let access = if watch.read { "read" } else { "write" };
+            format!(
+                "{:#05X}..{:#05X} ({})",
+                watch.range.start, watch.range.end, access
+            )
+        });
+        let names: Vec<_> = registers.chain(memory).collect();
+        format!("Watches: {}", names.join(" "))
+    }
+
+    // Any value which isn't a register is the address of a single byte
+    fn watch(&mut self, cpu: &mut CPU, target: &str) -> Result<String, String> {
+        let register = match Register::parse(target) {
+            Some(register) => register,
+            None => return self.watch_memory(cpu, target, "1", false),
+        };
+        if register == Register::Pc {
+            return Err(format!("Can't watch {}, use break instead", target));
+        }
+        if !self.watches.iter().any(|watch| watch.register == register) {
+            self.watches.push(Watch {
+                register,
+                value: register.get(cpu),
+            });
+        }
+        Ok(format!("Watching {}", register.name()))
+    }
+
+    fn watch_memory(
+        &mut self,
+        cpu: &mut CPU,
+        addr: &str,
+        len: &str,
+        read: bool,
+    ) -> Result<String, String> {
+        let start = value(cpu, addr)? as usize;
+        let end = start + value(cpu, len)?.max(1) as usize;
+        let target = if read {
+            WatchTarget::Read(start..end)
+        } else {
+            WatchTarget::Write(start..end)
+        };
+        let id = cpu.add_watchpoint(target, WatchAction::Halt);
+        self.memory_watches.push(MemoryWatch {
+            id,
+            range: start..end,
+            read,
+        });
+        let access = if read { "reads from" } else { "writes to" };
+        Ok(format!(
+            "Watching the {} {:#05X}..{:#05X}",
+            access, start, end
+        ))
+    }
+
+    fn unwatch(&mut self, cpu: &mut CPU, target: &str) -> Result<String, String> {
+        let register = match Register::parse(target) {
+            Some(register) => register,
+            None => return self.unwatch_memory(cpu, target),
+        };
+        let len = self.watches.len();
+        self.watches.retain(|watch| watch.register != register);
+        if self.watches.len() == len {
+            return Err(format!("{} is not watched", register.name()));
+        }
+        Ok(format!("Stopped watching {}", register.name()))
+    }
+
+    fn unwatch_memory(&mut self, cpu: &mut CPU, addr: &str) -> Result<String, String> {
+        let start = value(cpu, addr)? as usize;
+        let len = self.memory_watches.len();
+        self.memory_watches.retain(|watch| {
+            let keep = watch.range.start != start;
+            if !keep {
+                cpu.remove_watchpoint(watch.id);
+            }
+            keep
+        });
+        if self.memory_watches.len() == len {
+            return Err(format!("{:#05X} is not watched", start));
+        }
+        Ok(format!("Stopped watching {:#05X}", start))
+    }
+
+    // Remember the values the registers were changed to while stepping, so that they don't stop the program later on
+    fn update_watches(&mut self, cpu: &CPU) {
+        for watch in self.watches.iter_mut() {
+            watch.value = watch.register.get(cpu);
+        }
+    }
+
+    // INSPECTION
+    fn decode_at(&self, cpu: &CPU, addr: u16) -> Option<Instruction> {
+        let memory = cpu.memory();
+        let hi = *memory.get(addr as usize)?;
+        let lo = *memory.get(addr as usize + 1)?;
+        Instruction::decode(((hi as u16) << 8) | lo as u16)
+    }
+
+    fn instruction_at(&self, cpu: &CPU, addr: u16) -> String {
+        match self.decode_at(cpu, addr) {
+            Some(Instruction::LdILong) => match self.long_addr(cpu, addr) {
+                Some(long_addr) => format!("LD I, LONG {:#06X}", long_addr),
+                None => Instruction::LdILong.to_string(),
+            },
+            Some(instruction) => instruction.to_string(),
+            None => "???".to_string(),
+        }
+    }
+
+    fn long_addr(&self, cpu: &CPU, addr: u16) -> Option<u16> {
+        let memory = cpu.memory();
+        let hi = *memory.get(addr as usize + 2)?;
+        let lo = *memory.get(addr as usize + 3)?;
+        Some(((hi as u16) << 8) | lo as u16)
+    }
+
+    fn disasm(&self, cpu: &CPU, addr: &str, count: &str) -> Result<String, String> {
+        let mut addr = value(cpu, addr)?;
+        let count = value(cpu, count)?;
+        let mut output = String::new();
+        for _ in 0..count {
+            if addr as usize + 1 >= cpu.memory().len() {
+                break;
+            }
+            let marker = if addr == cpu.pc() { "=>" } else { "  " };
+            let breakpoint = if self.breakpoints.contains(&addr) {
+                "*"
+            } else {
+                " "
+            };
+            let _ = writeln!(
+                output,
+                "{}{} {:#05X}: {}",
+                marker,
+                breakpoint,
+                addr,
+                self.instruction_at(cpu, addr)
+            );
+            let size = self.decode_at(cpu, addr).map_or(2, |ins| ins.size());
+            addr = addr.wrapping_add(size);
+        }
+        Ok(output.trim_end().to_string())
+    }
+
+    fn set(&mut self, cpu: &mut CPU, name: &str, val: &str) -> Result<String, String> {
+        let register = Register::parse(name).ok_or_else(|| format!("Unknown register {}", name))?;
+        let val = value(cpu, val)?;
+        match register {
+            Register::V(x) => cpu.set_v_reg(x, val as u8),
+            Register::I => cpu.set_i_reg(val),
+            Register::Pc => cpu.set_pc(val),
+            Register::Dt => cpu.set_dt(val as u8),
+            Register::St => cpu.set_st(val as u8),
+        }
+        self.update_watches(cpu);
+        Ok(format!("{} = {:#04X}", register.name(), register.get(cpu)))
+    }
+}
+
+fn regs(cpu: &CPU) -> String {
+    let mut output = format!(
+        "PC {:#05X}  I {:#05X}  SP {}  DT {:#04X}  ST {:#04X}\n",
+        cpu.pc(),
+        cpu.i_reg(),
+        cpu.sp(),
+        cpu.dt(),
+        cpu.st()
+    );
+    for (x, value) in cpu.v_reg().iter().enumerate() {
+        let separator = if x % 8 == 7 { "\n" } else { "  " };
+        let _ = write!(output, "V{:X} {:#04X}{}", x, value, separator);
+    }
+    output.trim_end().to_string()
+}
+
+fn mem(cpu: &CPU, addr: &str, len: &str) -> Result<String, String> {
+    let addr = value(cpu, addr)? as usize;
+    let len = value(cpu, len)? as usize;
+    let memory = cpu.memory();
+    let end = (addr + len).min(memory.len());
+    if addr >= end {
+        return Err(format!("{:#05X} is past the end of the RAM", addr));
+    }
+    let mut output = String::new();
+    for (row, bytes) in memory[addr..end].chunks(8).enumerate() {
+        let bytes: Vec<_> = bytes.iter().map(|byte| format!("{:02X}", byte)).collect();
+        let _ = writeln!(output, "{:#05X}: {}", addr + row * 8, bytes.join(" "));
+    }
+    Ok(output.trim_end().to_string())
+}
+
+fn stack(cpu: &CPU) -> String {
+    if cpu.stack().is_empty() {
+        return "The stack is empty".to_string();
+    }
+    let mut output = String::new();
+    for (level, addr) in cpu.stack().iter().enumerate().rev() {
+        let _ = writeln!(output, "#{} {:#05X}", level, addr);
+    }
+    output.trim_end().to_string()
+}
+
+// Evaluate a sum of numbers and registers, such as `0x2A4`, `pc` or `i+2`
+fn value(cpu: &CPU, expr: &str) -> Result<u16, String> {
+    let mut total: u16 = 0;
+    for term in expr.split('+') {
+        let term = term.trim();
+        let number = if let Some(hex) = term.strip_prefix("0x").or(term.strip_prefix("0X")) {
+            u16::from_str_radix(hex, 16).ok()
+        } else if let Some(register) = Register::parse(term) {
+            Some(register.get(cpu))
+        } else {
+            term.parse().ok()
+        };
+        let number = number.ok_or_else(|| format!("Invalid value: {}", term))?;
+        total = total.wrapping_add(number);
+    }
+    Ok(total)
+}
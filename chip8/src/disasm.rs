@@ -0,0 +1,265 @@
+use crate::{Instruction, Platform, ALL_PLANES, PLANE_1, START_ADDR};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+// What a byte of the ROM was found to be
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Usage {
+    // Never reached by the program
+    Unknown,
+    // The first byte of an instruction
+    Code,
+    // One of the trailing bytes of an instruction
+    Operand,
+    // Drawn as a sprite by DXYN, after being pointed to by ANNN
+    Sprite,
+}
+
+// A single line of the disassembly
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Line {
+    // An instruction reached by the program
+    // For the XO-CHIP F000 NNNN, the long address is given as well
+    Code {
+        addr: u16,
+        instruction: Instruction,
+        long_addr: Option<u16>,
+    },
+    // A byte drawn as a sprite
+    Sprite {
+        addr: u16,
+        byte: u8,
+    },
+    // A byte which is never reached or drawn, and so is most likely data
+    Data {
+        addr: u16,
+        byte: u8,
+    },
+}
+
+impl Line {
+    pub fn addr(&self) -> u16 {
+        match *self {
+            Line::Code { addr, .. } | Line::Sprite { addr, .. } | Line::Data { addr, .. } => addr,
+        }
+    }
+}
+
+// The listing of a ROM, with labels generated for the jump and call targets, and for the sprites
+pub struct Disassembly {
+    lines: Vec<Line>,
+    labels: BTreeMap<u16, String>,
+}
+
+impl Disassembly {
+    // Disassemble a ROM, as it would be loaded by `CPU::load` and run on the given platform
+    // The code is found by following the flow of the program from its start, so anything else is considered data
+    pub fn new(rom: &[u8], platform: Platform) -> Self {
+        let start = START_ADDR as usize;
+        let end = start + rom.len();
+        let mut usage = vec![Usage::Unknown; rom.len()];
+        let mut labels = BTreeMap::new();
+
+        // Reads the OP Code at the given address, if it is fully inside the ROM
+        let op_at = |addr: usize| -> Option<u16> {
+            if addr < start || addr + 1 >= end {
+                return None;
+            }
+            Some(((rom[addr - start] as u16) << 8) | rom[addr + 1 - start] as u16)
+        };
+
+        // Follow every path the program can take, starting from its entry point
+        // The XO-CHIP planes selected along each path are passed on to the paths branching off it
+        let mut pending = vec![(start, PLANE_1)];
+        let mut visited = BTreeSet::new();
+        while let Some((block, mut planes)) = pending.pop() {
+            if !visited.insert(block) {
+                continue;
+            }
+
+            // The last address loaded in I along this path, to find the sprites drawn by DXYN
+            let mut i_reg: Option<usize> = None;
+            let mut addr = block;
+            while let Some(op) = op_at(addr) {
+                let instruction = match Instruction::decode(op) {
+                    Some(ins) if platform.supports(ins.platform()) => ins,
+                    _ => break,
+                };
+                let size = instruction.size() as usize;
+                // Stop once the path runs into code which was already followed, or into the middle of an instruction
+                let seen = addr != block && usage[addr - start] == Usage::Code;
+                if addr + size > end || seen || usage[addr - start] == Usage::Operand {
+                    break;
+                }
+                usage[addr - start] = Usage::Code;
+                for offset in 1..size {
+                    usage[addr + offset - start] = Usage::Operand;
+                }
+                let next = addr + size;
+
+                match instruction {
+                    // The flow continues at the target only
+                    Instruction::Jp(nnn) => {
+                        labels.insert(nnn, format!("L{:03X}", nnn));
+                        pending.push((nnn as usize, planes));
+                        break;
+                    }
+                    // The flow continues both at the target, and after the subroutine returns
+                    Instruction::Call(nnn) => {
+                        labels.insert(nnn, format!("L{:03X}", nnn));
+                        pending.push((nnn as usize, planes));
+                    }
+                    // The target can't be known without running the program
+                    Instruction::Ret | Instruction::Exit | Instruction::JpV0(_) => break,
+                    // The flow continues both at the next instruction, and at the one after
+                    Instruction::SeVxByte(..)
+                    | Instruction::SneVxByte(..)
+                    | Instruction::SeVxVy(..)
+                    | Instruction::SneVxVy(..)
+                    | Instruction::Skp(_)
+                    | Instruction::Sknp(_) => {
+                        let skipped = match op_at(next) {
+                            Some(0xF000) if platform.is_xo_chip() => 4,
+                            _ => 2,
+                        };
+                        pending.push((next + skipped, planes));
+                    }
+                    Instruction::LdI(nnn) => i_reg = Some(nnn as usize),
+                    Instruction::LdILong => {
+                        i_reg = op_at(addr + 2).map(|long_addr| long_addr as usize);
+                    }
+                    Instruction::Plane(n) => planes = n & ALL_PLANES,
+                    // On XO-CHIP, the sprite of each selected plane follows the one of the previous plane
+                    Instruction::Drw(_, _, n) => {
+                        if let Some(i) = i_reg {
+                            let big = n == 0 && platform.is_super_chip();
+                            let plane_len = if big { 32 } else { n as usize };
+                            let len = plane_len * planes.count_ones() as usize;
+                            if (start..end).contains(&i) {
+                                labels.entry(i as u16).or_insert(format!("S{:03X}", i));
+                            }
+                            for sprite in i..(i + len).min(end) {
+                                if sprite >= start && usage[sprite - start] == Usage::Unknown {
+                                    usage[sprite - start] = Usage::Sprite;
+                                }
+                            }
+                        }
+                    }
+                    // Any other change to I makes it unknown
+                    Instruction::AddIVx(_)
+                    | Instruction::LdFVx(_)
+                    | Instruction::LdHfVx(_)
+                    | Instruction::LdIVx(_)
+                    | Instruction::LdVxI(_) => i_reg = None,
+                    _ => (),
+                }
+
+                addr = next;
+            }
+        }
+
+        // Turn the usage of each byte into the lines of the listing
+        let mut lines = Vec::new();
+        let mut addr = start;
+        while addr < end {
+            let byte = rom[addr - start];
+            match usage[addr - start] {
+                Usage::Code => {
+                    let op = op_at(addr).unwrap_or_default();
+                    let instruction = Instruction::decode(op).unwrap_or(Instruction::Nop);
+                    let long_addr = match instruction {
+                        Instruction::LdILong => op_at(addr + 2),
+                        _ => None,
+                    };
+                    lines.push(Line::Code {
+                        addr: addr as u16,
+                        instruction,
+                        long_addr,
+                    });
+                    addr += instruction.size() as usize;
+                    continue;
+                }
+                Usage::Sprite => lines.push(Line::Sprite {
+                    addr: addr as u16,
+                    byte,
+                }),
+                Usage::Unknown | Usage::Operand => lines.push(Line::Data {
+                    addr: addr as u16,
+                    byte,
+                }),
+            }
+            addr += 1;
+        }
+
+        // Only keep the labels of the lines which are present in the listing
+        let starts: BTreeSet<u16> = lines.iter().map(|line| line.addr()).collect();
+        labels.retain(|addr, _| starts.contains(addr));
+
+        Self { lines, labels }
+    }
+
+    pub fn lines(&self) -> &[Line] {
+        &self.lines
+    }
+
+    // The label generated for an address, if it is the target of a jump, a call or a sprite load
+    pub fn label(&self, addr: u16) -> Option<&str> {
+        self.labels.get(&addr).map(|label| label.as_str())
+    }
+
+    // The mnemonic of an instruction, with its address operand replaced by the label when there is one
+    fn mnemonic(&self, instruction: Instruction, long_addr: Option<u16>) -> String {
+        match instruction {
+            Instruction::Jp(nnn) | Instruction::Call(nnn) | Instruction::LdI(nnn) => {
+                match self.label(nnn) {
+                    Some(label) => {
+                        let text = instruction.to_string();
+                        let (mnemonic, _) = text.rsplit_once(' ').unwrap_or((&text, ""));
+                        format!("{} {}", mnemonic, label)
+                    }
+                    None => instruction.to_string(),
+                }
+            }
+            Instruction::LdILong => match long_addr {
+                Some(long_addr) => match self.label(long_addr) {
+                    Some(label) => format!("LD I, LONG {}", label),
+                    None => format!("LD I, LONG {:#06X}", long_addr),
+                },
+                None => instruction.to_string(),
+            },
+            _ => instruction.to_string(),
+        }
+    }
+}
+
+// Prints the listing, one line per instruction or byte of data, such as `0x200: LD V0, 0x0F`
+impl fmt::Display for Disassembly {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for line in &self.lines {
+            if let Some(label) = self.label(line.addr()) {
+                writeln!(f, "{}:", label)?;
+            }
+            match *line {
+                Line::Code {
+                    addr,
+                    instruction,
+                    long_addr,
+                } => writeln!(
+                    f,
+                    "{:#05X}: {}",
+                    addr,
+                    self.mnemonic(instruction, long_addr)
+                )?,
+                Line::Sprite { addr, byte } => {
+                    // Show what the row of the sprite looks like as well
+                    let row: String = (0..8)
+                        .map(|bit| if byte & (0x80 >> bit) != 0 { '#' } else { '.' })
+                        .collect();
+                    writeln!(f, "{:#05X}: db {:#010b} ; {}", addr, byte, row)?
+                }
+                Line::Data { addr, byte } => writeln!(f, "{:#05X}: db {:#04X}", addr, byte)?,
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,74 @@
+use std::fmt;
+
+// All the ways in which executing a program on the CPU can fail
+// Returned by `CPU::tick` so that the frontends can stop cleanly instead of panicking
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Chip8Error {
+    // The OP Code fetched from `pc` does not correspond to any known instruction
+    UnknownOpcode { pc: u16, op: u16 },
+    // A CALL was made when all the slots of the stack were already used
+    StackOverflow,
+    // A RET was made when the stack was empty
+    StackUnderflow,
+    // An instruction tried to read or write RAM past its end
+    MemoryOutOfBounds { addr: usize },
+    // The Program Counter points outside the RAM, so no instruction can be fetched
+    PcOutOfBounds { pc: u16 },
+    // The ROM given to `CPU::load` does not fit in the RAM of the platform, after the interpreter area
+    RomTooLarge { size: usize, max: usize },
+}
+
+impl fmt::Display for Chip8Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Chip8Error::UnknownOpcode { pc, op } => {
+                write!(f, "unknown OP Code {:#06X} at {:#05X}", op, pc)
+            }
+            Chip8Error::StackOverflow => write!(f, "stack overflow"),
+            Chip8Error::StackUnderflow => write!(f, "stack underflow"),
+            Chip8Error::MemoryOutOfBounds { addr } => {
+                write!(f, "memory access out of bounds at {:#06X}", addr)
+            }
+            Chip8Error::PcOutOfBounds { pc } => {
+                write!(f, "program counter out of bounds at {:#06X}", pc)
+            }
+            Chip8Error::RomTooLarge { size, max } => {
+                write!(f, "ROM of {} bytes is too large, the most is {}", size, max)
+            }
+        }
+    }
+}
+
+impl std::error::Error for Chip8Error {}
+
+// All the ways in which restoring a save state (or reading a movie) can fail
+// Returned by `CPU::load_state`, in which case the CPU is left untouched, and by `Movie::from_bytes`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StateError {
+    // The data does not start with the magic header, so it is not a save state
+    BadMagic,
+    // The save state was written by another version of the emulator
+    UnsupportedVersion { version: u8 },
+    // The save state was made while running a different ROM than the one loaded
+    RomMismatch,
+    // The data ends before the whole state could be read
+    Truncated,
+    // One of the fields holds a value the CPU can never be in
+    Corrupted,
+}
+
+impl fmt::Display for StateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StateError::BadMagic => write!(f, "not a save state"),
+            StateError::UnsupportedVersion { version } => {
+                write!(f, "unsupported save state version {}", version)
+            }
+            StateError::RomMismatch => write!(f, "made while running a different ROM"),
+            StateError::Truncated => write!(f, "save state is truncated"),
+            StateError::Corrupted => write!(f, "save state is corrupted"),
+        }
+    }
+}
+
+impl std::error::Error for StateError {}
@@ -0,0 +1,296 @@
+use crate::Platform;
+use std::fmt;
+
+// A decoded OP Code
+// The operands are named after the digits of the OP Code they are taken from:
+// X and Y are register indexes, N is a 4 bit value, NN a byte and NNN a 12 bit address
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Instruction {
+    Nop,               // 0000
+    Cls,               // 00E0
+    Ret,               // 00EE
+    ScrollDown(u8),    // 00CN (SUPER-CHIP)
+    ScrollUp(u8),      // 00DN (XO-CHIP)
+    ScrollRight,       // 00FB (SUPER-CHIP)
+    ScrollLeft,        // 00FC (SUPER-CHIP)
+    Exit,              // 00FD (SUPER-CHIP)
+    Low,               // 00FE (SUPER-CHIP)
+    High,              // 00FF (SUPER-CHIP)
+    Jp(u16),           // 1NNN
+    Call(u16),         // 2NNN
+    SeVxByte(u8, u8),  // 3XNN
+    SneVxByte(u8, u8), // 4XNN
+    SeVxVy(u8, u8),    // 5XY0
+    SaveVxVy(u8, u8),  // 5XY2 (XO-CHIP)
+    LoadVxVy(u8, u8),  // 5XY3 (XO-CHIP)
+    LdVxByte(u8, u8),  // 6XNN
+    AddVxByte(u8, u8), // 7XNN
+    LdVxVy(u8, u8),    // 8XY0
+    OrVxVy(u8, u8),    // 8XY1
+    AndVxVy(u8, u8),   // 8XY2
+    XorVxVy(u8, u8),   // 8XY3
+    AddVxVy(u8, u8),   // 8XY4
+    SubVxVy(u8, u8),   // 8XY5
+    ShrVxVy(u8, u8),   // 8XY6
+    SubnVxVy(u8, u8),  // 8XY7
+    ShlVxVy(u8, u8),   // 8XYE
+    SneVxVy(u8, u8),   // 9XY0
+    LdI(u16),          // ANNN
+    JpV0(u16),         // BNNN
+    Rnd(u8, u8),       // CXNN
+    Drw(u8, u8, u8),   // DXYN
+    Skp(u8),           // EX9E
+    Sknp(u8),          // EXA1
+    LdILong,           // F000 NNNN (XO-CHIP), the address is the next 2 bytes of the program
+    Plane(u8),         // FN01 (XO-CHIP)
+    Audio,             // F002 (XO-CHIP)
+    LdVxDt(u8),        // FX07
+    LdVxK(u8),         // FX0A
+    LdDtVx(u8),        // FX15
+    LdStVx(u8),        // FX18
+    AddIVx(u8),        // FX1E
+    LdFVx(u8),         // FX29
+    LdHfVx(u8),        // FX30 (SUPER-CHIP)
+    LdBVx(u8),         // FX33
+    Pitch(u8),         // FX3A (XO-CHIP)
+    LdIVx(u8),         // FX55
+    LdVxI(u8),         // FX65
+    LdRVx(u8),         // FX75 (SUPER-CHIP)
+    LdVxR(u8),         // FX85 (SUPER-CHIP)
+}
+
+impl Instruction {
+    // Decode an OP Code into an instruction, or None if it doesn't correspond to any instruction of any platform
+    pub fn decode(op: u16) -> Option<Instruction> {
+        let d1 = (op & 0xF000) >> 12;
+        let d2 = (op & 0x0F00) >> 8;
+        let d3 = (op & 0x00F0) >> 4;
+        let d4 = op & 0x000F;
+
+        let x = d2 as u8;
+        let y = d3 as u8;
+        let n = d4 as u8;
+        let nn = (op & 0xFF) as u8;
+        let nnn = op & 0xFFF;
+
+        let instruction = match (d1, d2, d3, d4) {
+            (0, 0, 0, 0) => Instruction::Nop,
+            (0, 0, 0xE, 0) => Instruction::Cls,
+            (0, 0, 0xE, 0xE) => Instruction::Ret,
+            (0, 0, 0xC, _) => Instruction::ScrollDown(n),
+            (0, 0, 0xD, _) => Instruction::ScrollUp(n),
+            (0, 0, 0xF, 0xB) => Instruction::ScrollRight,
+            (0, 0, 0xF, 0xC) => Instruction::ScrollLeft,
+            (0, 0, 0xF, 0xD) => Instruction::Exit,
+            (0, 0, 0xF, 0xE) => Instruction::Low,
+            (0, 0, 0xF, 0xF) => Instruction::High,
+            (1, _, _, _) => Instruction::Jp(nnn),
+            (2, _, _, _) => Instruction::Call(nnn),
+            (3, _, _, _) => Instruction::SeVxByte(x, nn),
+            (4, _, _, _) => Instruction::SneVxByte(x, nn),
+            (5, _, _, 0) => Instruction::SeVxVy(x, y),
+            (5, _, _, 2) => Instruction::SaveVxVy(x, y),
+            (5, _, _, 3) => Instruction::LoadVxVy(x, y),
+            (6, _, _, _) => Instruction::LdVxByte(x, nn),
+            (7, _, _, _) => Instruction::AddVxByte(x, nn),
+            (8, _, _, 0) => Instruction::LdVxVy(x, y),
+            (8, _, _, 1) => Instruction::OrVxVy(x, y),
+            (8, _, _, 2) => Instruction::AndVxVy(x, y),
+            (8, _, _, 3) => Instruction::XorVxVy(x, y),
+            (8, _, _, 4) => Instruction::AddVxVy(x, y),
+            (8, _, _, 5) => Instruction::SubVxVy(x, y),
+            (8, _, _, 6) => Instruction::ShrVxVy(x, y),
+            (8, _, _, 7) => Instruction::SubnVxVy(x, y),
+            (8, _, _, 0xE) => Instruction::ShlVxVy(x, y),
+            (9, _, _, 0) => Instruction::SneVxVy(x, y),
+            (0xA, _, _, _) => Instruction::LdI(nnn),
+            (0xB, _, _, _) => Instruction::JpV0(nnn),
+            (0xC, _, _, _) => Instruction::Rnd(x, nn),
+            (0xD, _, _, _) => Instruction::Drw(x, y, n),
+            (0xE, _, 9, 0xE) => Instruction::Skp(x),
+            (0xE, _, 0xA, 1) => Instruction::Sknp(x),
+            (0xF, 0, 0, 0) => Instruction::LdILong,
+            (0xF, _, 0, 1) => Instruction::Plane(x),
+            (0xF, 0, 0, 2) => Instruction::Audio,
+            (0xF, _, 0, 7) => Instruction::LdVxDt(x),
+            (0xF, _, 0, 0xA) => Instruction::LdVxK(x),
+            (0xF, _, 1, 5) => Instruction::LdDtVx(x),
+            (0xF, _, 1, 8) => Instruction::LdStVx(x),
+            (0xF, _, 1, 0xE) => Instruction::AddIVx(x),
+            (0xF, _, 2, 9) => Instruction::LdFVx(x),
+            (0xF, _, 3, 0) => Instruction::LdHfVx(x),
+            (0xF, _, 3, 3) => Instruction::LdBVx(x),
+            (0xF, _, 3, 0xA) => Instruction::Pitch(x),
+            (0xF, _, 5, 5) => Instruction::LdIVx(x),
+            (0xF, _, 6, 5) => Instruction::LdVxI(x),
+            (0xF, _, 7, 5) => Instruction::LdRVx(x),
+            (0xF, _, 8, 5) => Instruction::LdVxR(x),
+            _ => return None,
+        };
+
+        Some(instruction)
+    }
+
+    // Encode the instruction back into its OP Code
+    // The operands are truncated to the number of digits the OP Code has room for
+    pub fn encode(&self) -> u16 {
+        // Build an OP Code from its digits
+        fn op(d1: u16, x: u8, y: u8, d4: u16) -> u16 {
+            (d1 << 12) | ((x as u16 & 0xF) << 8) | ((y as u16 & 0xF) << 4) | (d4 & 0xF)
+        }
+        // Build an OP Code from its first digit, and a 12 bit address
+        fn op_nnn(d1: u16, nnn: u16) -> u16 {
+            (d1 << 12) | (nnn & 0xFFF)
+        }
+        // Build an OP Code from its first digit, a register and a byte
+        fn op_xnn(d1: u16, x: u8, nn: u8) -> u16 {
+            (d1 << 12) | ((x as u16 & 0xF) << 8) | nn as u16
+        }
+
+        match *self {
+            Instruction::Nop => 0x0000,
+            Instruction::Cls => 0x00E0,
+            Instruction::Ret => 0x00EE,
+            Instruction::ScrollDown(n) => 0x00C0 | (n as u16 & 0xF),
+            Instruction::ScrollUp(n) => 0x00D0 | (n as u16 & 0xF),
+            Instruction::ScrollRight => 0x00FB,
+            Instruction::ScrollLeft => 0x00FC,
+            Instruction::Exit => 0x00FD,
+            Instruction::Low => 0x00FE,
+            Instruction::High => 0x00FF,
+            Instruction::Jp(nnn) => op_nnn(1, nnn),
+            Instruction::Call(nnn) => op_nnn(2, nnn),
+            Instruction::SeVxByte(x, nn) => op_xnn(3, x, nn),
+            Instruction::SneVxByte(x, nn) => op_xnn(4, x, nn),
+            Instruction::SeVxVy(x, y) => op(5, x, y, 0),
+            Instruction::SaveVxVy(x, y) => op(5, x, y, 2),
+            Instruction::LoadVxVy(x, y) => op(5, x, y, 3),
+            Instruction::LdVxByte(x, nn) => op_xnn(6, x, nn),
+            Instruction::AddVxByte(x, nn) => op_xnn(7, x, nn),
+            Instruction::LdVxVy(x, y) => op(8, x, y, 0),
+            Instruction::OrVxVy(x, y) => op(8, x, y, 1),
+            Instruction::AndVxVy(x, y) => op(8, x, y, 2),
+            Instruction::XorVxVy(x, y) => op(8, x, y, 3),
+            Instruction::AddVxVy(x, y) => op(8, x, y, 4),
+            Instruction::SubVxVy(x, y) => op(8, x, y, 5),
+            Instruction::ShrVxVy(x, y) => op(8, x, y, 6),
+            Instruction::SubnVxVy(x, y) => op(8, x, y, 7),
+            Instruction::ShlVxVy(x, y) => op(8, x, y, 0xE),
+            Instruction::SneVxVy(x, y) => op(9, x, y, 0),
+            Instruction::LdI(nnn) => op_nnn(0xA, nnn),
+            Instruction::JpV0(nnn) => op_nnn(0xB, nnn),
+            Instruction::Rnd(x, nn) => op_xnn(0xC, x, nn),
+            Instruction::Drw(x, y, n) => op(0xD, x, y, n as u16),
+            Instruction::Skp(x) => op_xnn(0xE, x, 0x9E),
+            Instruction::Sknp(x) => op_xnn(0xE, x, 0xA1),
+            Instruction::LdILong => 0xF000,
+            Instruction::Plane(x) => op_xnn(0xF, x, 0x01),
+            Instruction::Audio => 0xF002,
+            Instruction::LdVxDt(x) => op_xnn(0xF, x, 0x07),
+            Instruction::LdVxK(x) => op_xnn(0xF, x, 0x0A),
+            Instruction::LdDtVx(x) => op_xnn(0xF, x, 0x15),
+            Instruction::LdStVx(x) => op_xnn(0xF, x, 0x18),
+            Instruction::AddIVx(x) => op_xnn(0xF, x, 0x1E),
+            Instruction::LdFVx(x) => op_xnn(0xF, x, 0x29),
+            Instruction::LdHfVx(x) => op_xnn(0xF, x, 0x30),
+            Instruction::LdBVx(x) => op_xnn(0xF, x, 0x33),
+            Instruction::Pitch(x) => op_xnn(0xF, x, 0x3A),
+            Instruction::LdIVx(x) => op_xnn(0xF, x, 0x55),
+            Instruction::LdVxI(x) => op_xnn(0xF, x, 0x65),
+            Instruction::LdRVx(x) => op_xnn(0xF, x, 0x75),
+            Instruction::LdVxR(x) => op_xnn(0xF, x, 0x85),
+        }
+    }
+
+    // The first platform whose instruction set includes this instruction
+    pub fn platform(&self) -> Platform {
+        match self {
+            Instruction::ScrollDown(_)
+            | Instruction::ScrollRight
+            | Instruction::ScrollLeft
+            | Instruction::Exit
+            | Instruction::Low
+            | Instruction::High
+            | Instruction::LdHfVx(_)
+            | Instruction::LdRVx(_)
+            | Instruction::LdVxR(_) => Platform::SuperChip,
+
+            Instruction::ScrollUp(_)
+            | Instruction::SaveVxVy(_, _)
+            | Instruction::LoadVxVy(_, _)
+            | Instruction::LdILong
+            | Instruction::Plane(_)
+            | Instruction::Audio
+            | Instruction::Pitch(_) => Platform::XoChip,
+
+            _ => Platform::Chip8,
+        }
+    }
+
+    // Size of the instruction in the program, in bytes
+    pub fn size(&self) -> u16 {
+        match self {
+            Instruction::LdILong => 4,
+            _ => 2,
+        }
+    }
+}
+
+// Mnemonics of the instructions, with the addresses and bytes written in hex
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Instruction::Nop => write!(f, "NOP"),
+            Instruction::Cls => write!(f, "CLS"),
+            Instruction::Ret => write!(f, "RET"),
+            Instruction::ScrollDown(n) => write!(f, "SCD {}", n),
+            Instruction::ScrollUp(n) => write!(f, "SCU {}", n),
+            Instruction::ScrollRight => write!(f, "SCR"),
+            Instruction::ScrollLeft => write!(f, "SCL"),
+            Instruction::Exit => write!(f, "EXIT"),
+            Instruction::Low => write!(f, "LOW"),
+            Instruction::High => write!(f, "HIGH"),
+            Instruction::Jp(nnn) => write!(f, "JP {:#05X}", nnn),
+            Instruction::Call(nnn) => write!(f, "CALL {:#05X}", nnn),
+            Instruction::SeVxByte(x, nn) => write!(f, "SE V{:X}, {:#04X}", x, nn),
+            Instruction::SneVxByte(x, nn) => write!(f, "SNE V{:X}, {:#04X}", x, nn),
+            Instruction::SeVxVy(x, y) => write!(f, "SE V{:X}, V{:X}", x, y),
+            Instruction::SaveVxVy(x, y) => write!(f, "SAVE V{:X}, V{:X}", x, y),
+            Instruction::LoadVxVy(x, y) => write!(f, "LOAD V{:X}, V{:X}", x, y),
+            Instruction::LdVxByte(x, nn) => write!(f, "LD V{:X}, {:#04X}", x, nn),
+            Instruction::AddVxByte(x, nn) => write!(f, "ADD V{:X}, {:#04X}", x, nn),
+            Instruction::LdVxVy(x, y) => write!(f, "LD V{:X}, V{:X}", x, y),
+            Instruction::OrVxVy(x, y) => write!(f, "OR V{:X}, V{:X}", x, y),
+            Instruction::AndVxVy(x, y) => write!(f, "AND V{:X}, V{:X}", x, y),
+            Instruction::XorVxVy(x, y) => write!(f, "XOR V{:X}, V{:X}", x, y),
+            Instruction::AddVxVy(x, y) => write!(f, "ADD V{:X}, V{:X}", x, y),
+            Instruction::SubVxVy(x, y) => write!(f, "SUB V{:X}, V{:X}", x, y),
+            Instruction::ShrVxVy(x, y) => write!(f, "SHR V{:X}, V{:X}", x, y),
+            Instruction::SubnVxVy(x, y) => write!(f, "SUBN V{:X}, V{:X}", x, y),
+            Instruction::ShlVxVy(x, y) => write!(f, "SHL V{:X}, V{:X}", x, y),
+            Instruction::SneVxVy(x, y) => write!(f, "SNE V{:X}, V{:X}", x, y),
+            Instruction::LdI(nnn) => write!(f, "LD I, {:#05X}", nnn),
+            Instruction::JpV0(nnn) => write!(f, "JP V0, {:#05X}", nnn),
+            Instruction::Rnd(x, nn) => write!(f, "RND V{:X}, {:#04X}", x, nn),
+            Instruction::Drw(x, y, n) => write!(f, "DRW V{:X}, V{:X}, {}", x, y, n),
+            Instruction::Skp(x) => write!(f, "SKP V{:X}", x),
+            Instruction::Sknp(x) => write!(f, "SKNP V{:X}", x),
+            Instruction::LdILong => write!(f, "LD I, LONG"),
+            Instruction::Plane(n) => write!(f, "PLANE {}", n),
+            Instruction::Audio => write!(f, "AUDIO"),
+            Instruction::LdVxDt(x) => write!(f, "LD V{:X}, DT", x),
+            Instruction::LdVxK(x) => write!(f, "LD V{:X}, K", x),
+            Instruction::LdDtVx(x) => write!(f, "LD DT, V{:X}", x),
+            Instruction::LdStVx(x) => write!(f, "LD ST, V{:X}", x),
+            Instruction::AddIVx(x) => write!(f, "ADD I, V{:X}", x),
+            Instruction::LdFVx(x) => write!(f, "LD F, V{:X}", x),
+            Instruction::LdHfVx(x) => write!(f, "LD HF, V{:X}", x),
+            Instruction::LdBVx(x) => write!(f, "LD B, V{:X}", x),
+            Instruction::Pitch(x) => write!(f, "PITCH V{:X}", x),
+            Instruction::LdIVx(x) => write!(f, "LD [I], V{:X}", x),
+            Instruction::LdVxI(x) => write!(f, "LD V{:X}, [I]", x),
+            Instruction::LdRVx(x) => write!(f, "LD R, V{:X}", x),
+            Instruction::LdVxR(x) => write!(f, "LD V{:X}, R", x),
+        }
+    }
+}
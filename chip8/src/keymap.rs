@@ -0,0 +1,134 @@
+use crate::NUM_KEYS;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+// The keys of the keyboard bound to the CHIP-8 keys
+// The keys are named after their position, as the `code` of the keyboard events of the browsers (such as `KeyQ`,
+// `Digit1` or `ArrowUp`), so that a keymap is the same on QWERTY, AZERTY or Dvorak keyboards
+// Several keys can be bound to the same CHIP-8 key
+// The buttons of the gamepads are bound the same way, by their name
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Keymap(BTreeMap<String, u8>);
+
+/*
+    The keypad preset, the default one, keeps the layout of the COSMAC VIP keypad on the left of the keyboard
+
+    Keyboard                    Chip-8
+    +---+---+---+---+           +---+---+---+---+
+    | 1 | 2 | 3 | 4 |           | 1 | 2 | 3 | C |
+    +---+---+---+---+           +---+---+---+---+
+    | Q | W | E | R |           | 4 | 5 | 6 | D |
+    +---+---+---+---+     =>    +---+---+---+---+
+    | A | S | D | F |           | 7 | 8 | 9 | E |
+    +---+---+---+---+           +---+---+---+---+
+    | Z | X | C | V |           | A | 0 | B | F |
+    +---+---+---+---+           +---+---+---+---+
+*/
+const KEYPAD: [(&str, usize); NUM_KEYS] = [
+    ("Digit1", 0x1),
+    ("Digit2", 0x2),
+    ("Digit3", 0x3),
+    ("Digit4", 0xC),
+    ("KeyQ", 0x4),
+    ("KeyW", 0x5),
+    ("KeyE", 0x6),
+    ("KeyR", 0xD),
+    ("KeyA", 0x7),
+    ("KeyS", 0x8),
+    ("KeyD", 0x9),
+    ("KeyF", 0xE),
+    ("KeyZ", 0xA),
+    ("KeyX", 0x0),
+    ("KeyC", 0xB),
+    ("KeyV", 0xF),
+];
+
+// The numpad preset binds the digits to themselves, and A to F to the keys around them
+const NUMPAD: [(&str, usize); NUM_KEYS] = [
+    ("Numpad0", 0x0),
+    ("Numpad1", 0x1),
+    ("Numpad2", 0x2),
+    ("Numpad3", 0x3),
+    ("Numpad4", 0x4),
+    ("Numpad5", 0x5),
+    ("Numpad6", 0x6),
+    ("Numpad7", 0x7),
+    ("Numpad8", 0x8),
+    ("Numpad9", 0x9),
+    ("NumpadDivide", 0xA),
+    ("NumpadMultiply", 0xB),
+    ("NumpadSubtract", 0xC),
+    ("NumpadAdd", 0xD),
+    ("NumpadEnter", 0xE),
+    ("NumpadDecimal", 0xF),
+];
+
+impl Keymap {
+    pub const PRESETS: [&'static str; 2] = ["keypad", "numpad"];
+
+    // Parse and check a TOML file binding one key per line, such as `ArrowUp = 5` or `Space = 0xA`
+    pub fn parse(text: &str) -> Result<Keymap, String> {
+        let keymap: Keymap = toml::from_str(text).map_err(|err| err.to_string())?;
+        keymap.check()?;
+        Ok(keymap)
+    }
+
+    // The CHIP-8 key bound to a key of the keyboard
+    pub fn key(&self, code: &str) -> Option<usize> {
+        self.0.get(code).map(|&key| key as usize)
+    }
+
+    pub fn bind(&mut self, code: &str, key: usize) {
+        self.0.insert(code.to_string(), key as u8);
+    }
+
+    // Add the bindings of another keymap, which replace the ones of the same keys
+    pub fn extend(&mut self, other: &Keymap) {
+        self.0
+            .extend(other.0.iter().map(|(code, &key)| (code.clone(), key)));
+    }
+
+    // Whether all the keys are bound to CHIP-8 keys, from 0 to F
+    pub fn check(&self) -> Result<(), String> {
+        match self.0.iter().find(|(_, &key)| key as usize >= NUM_KEYS) {
+            Some((code, key)) => Err(format!(
+                "{} is bound to {}, which isn't a CHIP-8 key",
+                code, key
+            )),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        KEYPAD.into_iter().collect()
+    }
+}
+
+// The keys with the CHIP-8 keys they are bound to, such as `("KeyQ", 4)`
+impl<'a> FromIterator<(&'a str, usize)> for Keymap {
+    fn from_iter<T: IntoIterator<Item = (&'a str, usize)>>(bindings: T) -> Self {
+        let bindings = bindings.into_iter();
+        Keymap(
+            bindings
+                .map(|(code, key)| (code.to_string(), key as u8))
+                .collect(),
+        )
+    }
+}
+
+// The presets by name
+impl FromStr for Keymap {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "keypad" => Ok(KEYPAD.into_iter().collect()),
+            "numpad" => Ok(NUMPAD.into_iter().collect()),
+            _ => Err(format!("unknown keymap: {}", s)),
+        }
+    }
+}
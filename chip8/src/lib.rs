@@ -1,14 +1,53 @@
 use rand::random;
+use serde::Serialize;
+
+pub mod asm;
+mod audio;
+pub mod config;
+pub mod database;
+pub mod debugger;
+pub mod disasm;
+mod error;
+mod instruction;
+mod keymap;
+mod movie;
+mod platform;
+mod quirks;
+mod rewind;
+mod rng;
+mod state;
+#[cfg(test)]
+mod tests;
+mod trace;
+pub mod tracediff;
+mod watch;
+pub use audio::Audio;
+pub use error::{Chip8Error, StateError};
+pub use instruction::Instruction;
+pub use keymap::Keymap;
+pub use movie::{KeyEvent, Movie};
+pub use platform::Platform;
+pub use quirks::Quirks;
+pub use rewind::Rewind;
+pub use rng::Rng;
+pub use trace::Trace;
+pub use watch::{HitKind, WatchAction, WatchHit, WatchTarget};
 
 // SCREEN SIZE CONSTANTS
 pub const SCREEN_WIDTH: usize = 64;
 pub const SCREEN_HEIGHT: usize = 32;
+// Size of the screen in the SUPER-CHIP hi-res mode
+pub const HIRES_SCREEN_WIDTH: usize = 128;
+pub const HIRES_SCREEN_HEIGHT: usize = 64;
 
 // ARCHITECTURE CONSTANTS
 const RAM_SIZE: usize = 4096;
+const XO_RAM_SIZE: usize = 65536;
 const NUM_REGS: usize = 16;
 const STACK_SIZE: usize = 16;
 const NUM_KEYS: usize = 16;
+const NUM_RPL_FLAGS: usize = 16;
+const AUDIO_PATTERN_SIZE: usize = 16;
 
 // IMPLEMENTATION CONSTANTS
 const START_ADDR: u16 = 0x200;
@@ -37,28 +76,113 @@ const FONTSET: [u8; FONTSET_SIZE] = [
     0xF0, 0x80, 0xF0, 0x80, 0x80, // F
 ];
 
+// DEFINING THE BIG FONTSET used by SUPER-CHIP
+// 160 = 16 characters * 10 elements to display 10 rows
+// It is stored in the RAM right after the small FONTSET
+const BIG_FONTSET_ADDR: usize = FONTSET_SIZE;
+const BIG_FONTSET_SIZE: usize = 160;
+
+// Each row contains 8 bits
+const BIG_FONTSET: [u8; BIG_FONTSET_SIZE] = [
+    0x3C, 0x7E, 0xE7, 0xC3, 0xC3, 0xC3, 0xC3, 0xE7, 0x7E, 0x3C, // 0
+    0x18, 0x38, 0x58, 0x18, 0x18, 0x18, 0x18, 0x18, 0x18, 0x3C, // 1
+    0x3E, 0x7F, 0xC3, 0x06, 0x0C, 0x18, 0x30, 0x60, 0xFF, 0xFF, // 2
+    0x3C, 0x7E, 0xC3, 0x03, 0x0E, 0x0E, 0x03, 0xC3, 0x7E, 0x3C, // 3
+    0x06, 0x0E, 0x1E, 0x36, 0x66, 0xC6, 0xFF, 0xFF, 0x06, 0x06, // 4
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFC, 0xFE, 0x03, 0xC3, 0x7E, 0x3C, // 5
+    0x3E, 0x7C, 0xE0, 0xC0, 0xFC, 0xFE, 0xC3, 0xC3, 0x7E, 0x3C, // 6
+    0xFF, 0xFF, 0x03, 0x06, 0x0C, 0x18, 0x30, 0x60, 0x60, 0x60, // 7
+    0x3C, 0x7E, 0xC3, 0xC3, 0x7E, 0x7E, 0xC3, 0xC3, 0x7E, 0x3C, // 8
+    0x3C, 0x7E, 0xC3, 0xC3, 0x7F, 0x3F, 0x03, 0x03, 0x3E, 0x7C, // 9
+    0x3C, 0x7E, 0xC3, 0xC3, 0xFF, 0xFF, 0xC3, 0xC3, 0xC3, 0xC3, // A
+    0xFC, 0xFE, 0xC3, 0xC3, 0xFE, 0xFE, 0xC3, 0xC3, 0xFE, 0xFC, // B
+    0x3C, 0x7E, 0xC3, 0xC0, 0xC0, 0xC0, 0xC0, 0xC3, 0x7E, 0x3C, // C
+    0xFC, 0xFE, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xFE, 0xFC, // D
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFC, 0xFC, 0xC0, 0xC0, 0xFF, 0xFF, // E
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFC, 0xFC, 0xC0, 0xC0, 0xC0, 0xC0, // F
+];
+
+// XO-CHIP BITPLANES
+// Each pixel of the screen holds one bit per plane, so that it can take one of 4 colors
+const PLANE_1: u8 = 0b01;
+const PLANE_2: u8 = 0b10;
+const ALL_PLANES: u8 = PLANE_1 | PLANE_2;
+
+// The default XO-CHIP audio pattern, a square wave
+const DEFAULT_AUDIO_PATTERN: [u8; AUDIO_PATTERN_SIZE] = [
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
+];
+const DEFAULT_PITCH: u8 = 64;
+
+// The screen as sent to the frontend, along with its current dimensions
+pub struct Display<'a> {
+    pub width: usize,
+    pub height: usize,
+    // Row-major pixels, `width * height` in size
+    // Each pixel is a color from 0 to 3, where bit 0 is plane 1 and bit 1 is plane 2
+    // Programs which don't use XO-CHIP's second plane only ever have the colors 0 and 1
+    pub pixels: &'a [u8],
+}
+
+// A copy of the registers, the stack, the timers and the keys, as returned by `CPU::cpu_state`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CpuState {
+    pub pc: u16,
+    pub i_reg: u16,
+    pub v_reg: [u8; NUM_REGS],
+    // The return addresses currently on the stack, the latest one last, so the stack pointer is its length
+    pub stack: Vec<u16>,
+    pub dt: u8,
+    pub st: u8,
+    pub keys: [bool; NUM_KEYS],
+    pub hires: bool,
+    // The XO-CHIP bitplanes selected for drawing, bit 0 being plane 1
+    pub planes: u8,
+    pub halted: bool,
+}
+
 // The actual emulator class
 pub struct CPU {
-    pc: u16,                                      // Program Counter
-    ram: [u8; RAM_SIZE],                          // RAM
-    screen: [bool; SCREEN_WIDTH * SCREEN_HEIGHT], // Screen Pixels
-    v_reg: [u8; NUM_REGS],                        // V Registers
-    i_reg: u16,                                   // Instruction Register
-    sp: u16,                                      // Stack Pointer
-    stack: [u16; STACK_SIZE],                     // Stack
-    keys: [bool; NUM_KEYS],                       // Keys
-    dt: u8,                                       // Delay Timer
-    st: u8,                                       // Stack Timer
+    pc: u16,                                 // Program Counter
+    ram: Vec<u8>,                            // RAM, sized for the platform
+    screen: Vec<u8>,                         // Screen Pixels, sized for the current resolution
+    hires: bool,                             // SUPER-CHIP hi-res mode
+    planes: u8,                              // XO-CHIP bitplanes selected for drawing
+    v_reg: [u8; NUM_REGS],                   // V Registers
+    i_reg: u16,                              // Instruction Register
+    sp: u16,                                 // Stack Pointer
+    stack: [u16; STACK_SIZE],                // Stack
+    keys: [bool; NUM_KEYS],                  // Keys
+    dt: u8,                                  // Delay Timer
+    st: u8,                                  // Stack Timer
+    rpl: [u8; NUM_RPL_FLAGS],                // SUPER-CHIP RPL User Flags
+    audio_pattern: [u8; AUDIO_PATTERN_SIZE], // XO-CHIP Audio Pattern Buffer
+    pitch: u8,                               // XO-CHIP Audio Pitch
+    halted: bool,                            // Set once the program exits
+    platform: Platform,                      // Instruction set being emulated
+    quirks: Quirks,                          // Behaviour of the ambiguous instructions
+    vblank: bool,                            // Set on every frame, for the display wait quirk
+    rom_hash: [u8; 20],                      // SHA-1 of the loaded ROM
+    rng: Rng,                                // Random number generator for CXNN
+    watchpoints: watch::Watchpoints,         // Watched RAM ranges and registers
+    trace: Option<Trace>,                    // Trace of the instructions, if enabled
 }
 
 // Deals with the most basic fucntionality that involves with instanstiating an emulator
 impl CPU {
     // Constructor method to initialize a new instance
     pub fn new() -> Self {
+        Self::with_quirks(Quirks::default())
+    }
+
+    // Constructor method to initialize a new instance, which runs the programs with the given quirks
+    pub fn with_quirks(quirks: Quirks) -> Self {
         let mut new_cpu = Self {
             pc: START_ADDR,
-            ram: [0; RAM_SIZE],
-            screen: [false; SCREEN_WIDTH * SCREEN_HEIGHT],
+            ram: vec![0; RAM_SIZE],
+            screen: vec![0; SCREEN_WIDTH * SCREEN_HEIGHT],
+            hires: false,
+            planes: PLANE_1,
             v_reg: [0; NUM_REGS],
             i_reg: 0,
             sp: 0,
@@ -66,19 +190,48 @@ impl CPU {
             keys: [false; NUM_KEYS],
             dt: 0,
             st: 0,
+            rpl: [0; NUM_RPL_FLAGS],
+            audio_pattern: DEFAULT_AUDIO_PATTERN,
+            pitch: DEFAULT_PITCH,
+            halted: false,
+            platform: Platform::Chip8,
+            quirks,
+            vblank: false,
+            rom_hash: [0; 20],
+            rng: Rng::new(random()),
+            watchpoints: watch::Watchpoints::default(),
+            trace: None,
         };
 
-        // Copy the FONTSET into the starting location of the RAM of the CPU
-        new_cpu.ram[..FONTSET_SIZE].copy_from_slice(&FONTSET);
+        // Copy the FONTSETs into the starting location of the RAM of the CPU
+        new_cpu.load_fontsets();
 
         new_cpu
     }
 
+    // Constructor method to initialize a new instance whose random numbers are generated from the given seed
+    // Given the same program and the same input, two instances with the same seed run identically
+    pub fn with_seed(seed: u64) -> Self {
+        let mut new_cpu = Self::new();
+        new_cpu.set_seed(seed);
+        new_cpu
+    }
+
+    // Constructor method to initialize a new instance for the given platform, with the quirks its programs usually expect
+    pub fn with_platform(platform: Platform) -> Self {
+        let mut new_cpu = Self::with_quirks(platform.quirks());
+        new_cpu.set_platform(platform);
+        new_cpu
+    }
+
     // Reset the CPU to the original state
+    // The platform, quirks and RPL user flags (which are persistent on the real hardware) are kept
     pub fn reset(&mut self) {
         self.pc = START_ADDR;
-        self.ram = [0; RAM_SIZE];
-        self.screen = [false; SCREEN_WIDTH * SCREEN_HEIGHT];
+        self.ram = vec![0; self.platform.ram_size()];
+        self.hires = false;
+        self.screen = vec![0; SCREEN_WIDTH * SCREEN_HEIGHT];
+        self.planes = PLANE_1;
         self.v_reg = [0; NUM_REGS];
         self.i_reg = 0;
         self.sp = 0;
@@ -86,60 +239,222 @@ impl CPU {
         self.keys = [false; NUM_KEYS];
         self.dt = 0;
         self.st = 0;
-        // Load the FONTSET into the inital addresses of the RAM
+        self.audio_pattern = DEFAULT_AUDIO_PATTERN;
+        self.pitch = DEFAULT_PITCH;
+        self.halted = false;
+        self.vblank = false;
+        self.rom_hash = [0; 20];
+        // Load the FONTSETs into the inital addresses of the RAM
+        self.load_fontsets();
+    }
+
+    fn load_fontsets(&mut self) {
         self.ram[..FONTSET_SIZE].copy_from_slice(&FONTSET);
+        self.ram[BIG_FONTSET_ADDR..BIG_FONTSET_ADDR + BIG_FONTSET_SIZE]
+            .copy_from_slice(&BIG_FONTSET);
+    }
+}
+
+// Deals with the platform and quirks the CPU runs the programs with
+impl CPU {
+    pub fn platform(&self) -> Platform {
+        self.platform
+    }
+
+    // Changing the platform resets the CPU, so it must be done before loading a program
+    // The quirks are left untouched, use `Platform::quirks` to get the usual ones for the platform
+    pub fn set_platform(&mut self, platform: Platform) {
+        self.platform = platform;
+        self.reset();
+    }
+
+    pub fn quirks(&self) -> Quirks {
+        self.quirks
+    }
+
+    // The quirks can be changed at any point, and apply from the next instruction onwards
+    pub fn set_quirks(&mut self, quirks: Quirks) {
+        self.quirks = quirks;
+    }
+
+    // Restart the random numbers of CXNN from the given seed
+    // The random number generator is not affected by `reset`, so this can be done before or after loading a program
+    pub fn set_seed(&mut self, seed: u64) {
+        self.rng = Rng::new(seed);
+    }
+
+    // Whether the program has exited through the SUPER-CHIP 00FD instruction
+    pub fn is_halted(&self) -> bool {
+        self.halted
+    }
+}
+
+// Deals with the dimensions and the scrolling of the screen
+impl CPU {
+    fn screen_width(&self) -> usize {
+        if self.hires {
+            HIRES_SCREEN_WIDTH
+        } else {
+            SCREEN_WIDTH
+        }
+    }
+
+    fn screen_height(&self) -> usize {
+        if self.hires {
+            HIRES_SCREEN_HEIGHT
+        } else {
+            SCREEN_HEIGHT
+        }
+    }
+
+    // Switch between the lo-res and the hi-res mode, which also clears the screen
+    fn set_hires(&mut self, hires: bool) {
+        self.hires = hires;
+        self.screen = vec![0; self.screen_width() * self.screen_height()];
+    }
+
+    // Scroll the selected planes of the screen by the given number of pixels, filling the vacated pixels with 0
+    // Positive values scroll right/down, while negative values scroll left/up
+    fn scroll(&mut self, dx: isize, dy: isize) {
+        let width = self.screen_width() as isize;
+        let height = self.screen_height() as isize;
+        let old = self.screen.clone();
+
+        for y in 0..height {
+            for x in 0..width {
+                let (src_x, src_y) = (x - dx, y - dy);
+                let inside = (0..width).contains(&src_x) && (0..height).contains(&src_y);
+                let moved = if inside {
+                    old[(src_x + src_y * width) as usize] & self.planes
+                } else {
+                    0
+                };
+                let idx = (x + y * width) as usize;
+                self.screen[idx] = (self.screen[idx] & !self.planes) | moved;
+            }
+        }
+    }
+}
+
+impl Default for CPU {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
 // This block deals all the STACK operations
-// TODO: No error handling is added for the underflow and overflow of stack. The same must be implemented
 impl CPU {
-    fn push(&mut self, val: u16) {
+    fn push(&mut self, val: u16) -> Result<(), Chip8Error> {
+        if self.sp as usize >= STACK_SIZE {
+            return Err(Chip8Error::StackOverflow);
+        }
         self.stack[self.sp as usize] = val;
         self.sp += 1;
+        Ok(())
     }
 
-    fn pop(&mut self) -> u16 {
+    fn pop(&mut self) -> Result<u16, Chip8Error> {
+        if self.sp == 0 {
+            return Err(Chip8Error::StackUnderflow);
+        }
         self.sp -= 1;
-        self.stack[self.sp as usize]
+        Ok(self.stack[self.sp as usize])
+    }
+}
+
+// This block deals with the bounds-checked access to the RAM
+impl CPU {
+    fn read(&mut self, addr: usize) -> Result<u8, Chip8Error> {
+        let val = self
+            .ram
+            .get(addr)
+            .copied()
+            .ok_or(Chip8Error::MemoryOutOfBounds { addr })?;
+        if !self.watchpoints.is_empty() {
+            self.watchpoints.read(addr, val);
+        }
+        Ok(val)
+    }
+
+    fn write(&mut self, addr: usize, val: u8) -> Result<(), Chip8Error> {
+        let cell = self
+            .ram
+            .get_mut(addr)
+            .ok_or(Chip8Error::MemoryOutOfBounds { addr })?;
+        let old = std::mem::replace(cell, val);
+        if !self.watchpoints.is_empty() {
+            self.watchpoints.write(addr, old, val);
+        }
+        Ok(())
     }
 }
 
 // Deals with each CYCLE of the emualator life
 impl CPU {
     // Simulates one clock cycle
-    pub fn tick(&mut self) {
-        let op = self.fetch();
-        self.execute(op);
+    // If the instruction can't be executed, the error is returned and the emulation should be stopped
+    pub fn tick(&mut self) -> Result<(), Chip8Error> {
+        // Once the program has exited, or a watchpoint has halted it, there is nothing left to run
+        if self.halted || self.watchpoints.hit.is_some() {
+            return Ok(());
+        }
+        if let Some(mut trace) = self.trace.take() {
+            trace.record(self);
+            self.trace = Some(trace);
+        }
+        if self.watchpoints.is_empty() {
+            let op = self.fetch()?;
+            return self.execute(op);
+        }
+
+        // The registers are compared with their values from before the instruction, once it is done
+        let (v_reg, i_reg) = (self.v_reg, self.i_reg);
+        self.watchpoints.pc = self.pc;
+        let op = self.fetch()?;
+        let result = self.execute(op);
+        self.watchpoints
+            .registers(&v_reg, i_reg, &self.v_reg, self.i_reg);
+        result
     }
 
     // Fetch the instruction from the program (which will be loaded into RAM) at the memory address stored in the Program Counter
-    fn fetch(&mut self) -> u16 {
+    fn fetch(&mut self) -> Result<u16, Chip8Error> {
         // Each OP Code is just 2 bytes in size
-        let higher_byte = self.ram[self.pc as usize] as u16;
-        let lower_byte = self.ram[(self.pc + 1) as usize] as u16;
+        let pc = self.pc as usize;
+        if pc + 1 >= self.ram.len() {
+            return Err(Chip8Error::PcOutOfBounds { pc: self.pc });
+        }
+        let higher_byte = self.ram[pc] as u16;
+        let lower_byte = self.ram[pc + 1] as u16;
         let op = (higher_byte << 8) | lower_byte;
 
         // Increment the program counter by two bytes
-        self.pc += 2;
+        self.pc = self.pc.wrapping_add(2);
+
+        Ok(op)
+    }
 
-        op
+    // Skip over the next instruction, which is 4 bytes long if it is the XO-CHIP F000 NNNN
+    fn skip_next(&mut self) {
+        let pc = self.pc as usize;
+        let long = self.platform.is_xo_chip()
+            && self.ram.get(pc) == Some(&0xF0)
+            && self.ram.get(pc + 1) == Some(&0x00);
+        self.pc = self.pc.wrapping_add(if long { 4 } else { 2 });
     }
 
     // Work with the two timer flags
     pub fn tick_timers(&mut self) {
+        // A new frame has started, so a sprite waiting on the display can be drawn
+        self.vblank = true;
+
         // Delay timer is decremented by 1 in each cycle until it reaches 0 and is ready to be executed
         if self.dt > 0 {
             self.dt -= 1;
         }
 
-        // The sound timer beeps when the same is 1, and is decremented by 1 in each cycle
+        // The sound timer is decremented by 1 in each cycle, and the buzzer sounds for as long as it isn't 0
         if self.st > 0 {
-            if self.st == 1 {
-                // BEEP! Simulated by a print line statement
-                // TODO: Implement actual sound of a beep here
-                println!("BEEP!");
-            }
             self.st -= 1;
         }
     }
@@ -148,27 +463,36 @@ impl CPU {
 // Deals with all of the OP Code Execution
 impl CPU {
     // Execute the instruction corresponding to a particular hex code
-    fn execute(&mut self, op: u16) {
-        let d1 = (op & 0xF000) >> 12;
-        let d2 = (op & 0x0F00) >> 8;
-        let d3 = (op & 0x00F0) >> 4;
-        let d4 = op & 0x000F;
+    fn execute(&mut self, op: u16) -> Result<(), Chip8Error> {
+        // The OP Code must be a valid instruction for the platform being emulated
+        let instruction = match Instruction::decode(op) {
+            Some(instruction) if self.platform.supports(instruction.platform()) => instruction,
+            _ => {
+                return Err(Chip8Error::UnknownOpcode {
+                    pc: self.pc.wrapping_sub(2),
+                    op,
+                })
+            }
+        };
 
-        match (d1, d2, d3, d4) {
+        match instruction {
             /*
                0000
                NOP Instruction
                Do nothing, move onto the next instruction
             */
-            (0, 0, 0, 0) => return,
+            Instruction::Nop => (),
 
             /*
                 00EO
                 CLS Instruction
                 Used to clear the screen, and set all the pixels to 0
+                On XO-CHIP, only the selected planes are cleared
             */
-            (0, 0, 0xE, 0) => {
-                self.screen = [false; SCREEN_WIDTH * SCREEN_HEIGHT];
+            Instruction::Cls => {
+                for pixel in self.screen.iter_mut() {
+                    *pixel &= !self.planes;
+                }
             }
 
             /*
@@ -177,19 +501,81 @@ impl CPU {
                 When entering a subroutine, we push the address onto the stack and then run the routine's code
                 To return, we pop that value off our stack and execute from that point again
             */
-            (0, 0, 0xE, 0xE) => {
-                let ret_addr = self.pop();
+            Instruction::Ret => {
+                let ret_addr = self.pop()?;
                 self.pc = ret_addr;
             }
 
+            /*
+                00CN
+                Scroll Down Instruction (SUPER-CHIP)
+                Scrolls the whole screen down by N pixels
+            */
+            Instruction::ScrollDown(n) => {
+                self.scroll(0, n as isize);
+            }
+
+            /*
+                00DN
+                Scroll Up Instruction (XO-CHIP)
+                Scrolls the whole screen up by N pixels
+            */
+            Instruction::ScrollUp(n) => {
+                self.scroll(0, -(n as isize));
+            }
+
+            /*
+                00FB
+                Scroll Right Instruction (SUPER-CHIP)
+                Scrolls the whole screen right by 4 pixels
+            */
+            Instruction::ScrollRight => {
+                self.scroll(4, 0);
+            }
+
+            /*
+                00FC
+                Scroll Left Instruction (SUPER-CHIP)
+                Scrolls the whole screen left by 4 pixels
+            */
+            Instruction::ScrollLeft => {
+                self.scroll(-4, 0);
+            }
+
+            /*
+                00FD
+                Exit Instruction (SUPER-CHIP)
+                Stops the execution of the program
+            */
+            Instruction::Exit => {
+                self.halted = true;
+            }
+
+            /*
+                00FE
+                Lo-Res Instruction (SUPER-CHIP)
+                Switches the screen to the 64x32 mode and clears it
+            */
+            Instruction::Low => {
+                self.set_hires(false);
+            }
+
+            /*
+                00FF
+                Hi-Res Instruction (SUPER-CHIP)
+                Switches the screen to the 128x64 mode and clears it
+            */
+            Instruction::High => {
+                self.set_hires(true);
+            }
+
             /*
                 1NNN
                 JMP Instruction
                 Used to jump to a particular instruction
                 Only the most significant digit needs to be set, the rest are used as operand (specify with instruction to jump to)
             */
-            (1, _, _, _) => {
-                let nnn = op & 0xFFF;
+            Instruction::Jp(nnn) => {
                 self.pc = nnn;
             }
 
@@ -199,9 +585,8 @@ impl CPU {
                 Used to enter a subroutine
                 The current value is stored in the stack, and the jump to the adress provided by the last 3 digits is made
             */
-            (2, _, _, _) => {
-                let nnn = op & 0xFFF;
-                self.push(self.pc);
+            Instruction::Call(nnn) => {
+                self.push(self.pc)?;
                 self.pc = nnn;
             }
 
@@ -209,13 +594,11 @@ impl CPU {
                 3XNN
                 SKIP if Equal Instruction
                 Conditional instruction
-                Used to skip one instruction (2 bytes in PC) if the register V[d2] has the value designated by d3 and d4
+                Used to skip one instruction (2 bytes in PC) if the register V[X] has the value NN
             */
-            (3, _, _, _) => {
-                let x = d2 as usize;
-                let nn = (op & 0xFF) as u8;
-                if self.v_reg[x] == nn {
-                    self.pc += 2;
+            Instruction::SeVxByte(x, nn) => {
+                if self.v_reg[x as usize] == nn {
+                    self.skip_next();
                 }
             }
 
@@ -223,13 +606,11 @@ impl CPU {
                 4XNN
                 SKIP if Not Equal Instruction
                 Conditional instruction
-                Used to skip one instruction (2 bytes in PC) if the register V[d2] does not have the value equal designated by d3 and d4
+                Used to skip one instruction (2 bytes in PC) if the register V[X] does not have the value NN
             */
-            (4, _, _, _) => {
-                let x = d2 as usize;
-                let nn = (op & 0xFF) as u8;
-                if self.v_reg[x] != nn {
-                    self.pc += 2;
+            Instruction::SneVxByte(x, nn) => {
+                if self.v_reg[x as usize] != nn {
+                    self.skip_next();
                 }
             }
 
@@ -237,35 +618,56 @@ impl CPU {
                 5XY0
                 SKIP if Registers are Equal Instruction
                 Conditional instruction
-                Used to skip one instruction (2 bytes in PC) if register V[d2] == register V[d3]
+                Used to skip one instruction (2 bytes in PC) if register V[X] == register V[Y]
+            */
+            Instruction::SeVxVy(x, y) => {
+                if self.v_reg[x as usize] == self.v_reg[y as usize] {
+                    self.skip_next();
+                }
+            }
+
+            /*
+                5XY2
+                Store Register Range into RAM Instruction (XO-CHIP)
+                Stores the registers from V[X] to V[Y] (in reverse if X > Y) in RAM, starting from the address pointed by IR
+                IR itself is left untouched
             */
-            (5, _, _, 0) => {
-                let x = d2 as usize;
-                let y = d3 as usize;
-                if self.v_reg[x] == self.v_reg[y] {
-                    self.pc += 2;
+            Instruction::SaveVxVy(x, y) => {
+                let i = self.i_reg as usize;
+                for (offset, reg) in register_range(x as usize, y as usize).enumerate() {
+                    self.write(i + offset, self.v_reg[reg])?;
+                }
+            }
+
+            /*
+                5XY3
+                Load Register Range from RAM Instruction (XO-CHIP)
+                Loads the registers from V[X] to V[Y] (in reverse if X > Y) from RAM, starting from the address pointed by IR
+                IR itself is left untouched
+            */
+            Instruction::LoadVxVy(x, y) => {
+                let i = self.i_reg as usize;
+                for (offset, reg) in register_range(x as usize, y as usize).enumerate() {
+                    self.v_reg[reg] = self.read(i + offset)?;
                 }
             }
 
             /*
                 6XNN
                 Set Register Instruction
-                Set register V[d2] equal to the value desginated by d3 and d4
+                Set register V[X] equal to the value NN
             */
-            (6, _, _, _) => {
-                let x = d2 as usize;
-                let nn = (op & 0xFF) as u8;
-                self.v_reg[x] = nn;
+            Instruction::LdVxByte(x, nn) => {
+                self.v_reg[x as usize] = nn;
             }
 
             /*
                 7XNN
                 Increment Register Instruction
-                Increment register V[d2] by the value desginated by d3 and d4
+                Increment register V[X] by the value NN
             */
-            (7, _, _, _) => {
-                let x = d2 as usize;
-                let nn = (op & 0xFF) as u8;
+            Instruction::AddVxByte(x, nn) => {
+                let x = x as usize;
                 // The wrapping add method is used to prevent Rust from panicking if an overflow occurs, and to wrap around
                 // No overflow flag is used by this instruction
                 self.v_reg[x] = self.v_reg[x].wrapping_add(nn);
@@ -274,34 +676,60 @@ impl CPU {
             /*
                 8XY0
                 Set Register Instruction
-                Set register V[d2] = register V[d3]
+                Set register V[X] = register V[Y]
             */
-            (8, _, _, 0) => {
-                let x = d2 as usize;
-                let y = d3 as usize;
-                self.v_reg[x] = self.v_reg[y];
+            Instruction::LdVxVy(x, y) => {
+                self.v_reg[x as usize] = self.v_reg[y as usize];
             }
 
             /*
-                8XY1, 8XY2, 8XY3
+                8XY1
                 Bitwise OR
-                Set V[d2] = V[d2] | V[d3]
+                Set V[X] = V[X] | V[Y]
+                With the VF reset quirk, V[15] is set to 0 as well
+            */
+            Instruction::OrVxVy(x, y) => {
+                self.v_reg[x as usize] |= self.v_reg[y as usize];
+                if self.quirks.vf_reset {
+                    self.v_reg[0xF] = 0;
+                }
+            }
+
+            /*
+                8XY2
+                Bitwise AND
+                Set V[X] = V[X] & V[Y]
+                With the VF reset quirk, V[15] is set to 0 as well
+            */
+            Instruction::AndVxVy(x, y) => {
+                self.v_reg[x as usize] &= self.v_reg[y as usize];
+                if self.quirks.vf_reset {
+                    self.v_reg[0xF] = 0;
+                }
+            }
+
+            /*
+                8XY3
+                Bitwise XOR
+                Set V[X] = V[X] ^ V[Y]
+                With the VF reset quirk, V[15] is set to 0 as well
             */
-            (8, _, _, 1) | (8, _, _, 2) | (8, _, _, 3) => {
-                let x = d2 as usize;
-                let y = d3 as usize;
-                self.v_reg[x] |= self.v_reg[y];
+            Instruction::XorVxVy(x, y) => {
+                self.v_reg[x as usize] ^= self.v_reg[y as usize];
+                if self.quirks.vf_reset {
+                    self.v_reg[0xF] = 0;
+                }
             }
 
             /*
                 8XY4
                 Add Registers with Overflow Instruction
-                Sets V[d2] = V[d2] + V[d3]
+                Sets V[X] = V[X] + V[Y]
                 The last register (V[15]) is used the flag bit, while the others are used as general purpose registers
             */
-            (8, _, _, 4) => {
-                let x = d2 as usize;
-                let y = d3 as usize;
+            Instruction::AddVxVy(x, y) => {
+                let x = x as usize;
+                let y = y as usize;
 
                 let (sum, carry) = self.v_reg[x].overflowing_add(self.v_reg[y]);
                 let carry = if carry { 1 } else { 0 };
@@ -313,15 +741,15 @@ impl CPU {
             /*
                 8XY5
                 Subtract Registers with Overflow Instruction
-                Sets V[d2] = V[d2] - V[d3]
-                The last register (V[15]) is used the flag bit
+                Sets V[X] = V[X] - V[Y]
+                The last register (V[15]) is used the flag bit, which is set to 0 when there is a borrow and to 1 otherwise
             */
-            (8, _, _, 5) => {
-                let x = d2 as usize;
-                let y = d3 as usize;
+            Instruction::SubVxVy(x, y) => {
+                let x = x as usize;
+                let y = y as usize;
 
-                let (diff, borrow) = self.v_reg[x].overflowing_add(self.v_reg[y]);
-                let borrow = if borrow { 1 } else { 0 };
+                let (diff, borrow) = self.v_reg[x].overflowing_sub(self.v_reg[y]);
+                let borrow = if borrow { 0 } else { 1 };
 
                 self.v_reg[x] = diff;
                 self.v_reg[0xF] = borrow;
@@ -330,25 +758,27 @@ impl CPU {
             /*
                 8XY6
                 Right Shift in Register Instruction
-                Sets V[d2] = V[d2] >> 1
+                Sets V[X] = V[X] >> 1
+                Without the shift quirk, V[Y] is shifted instead, that is V[X] = V[Y] >> 1
                 The last register (V[15]) is used the flag bit, which is used to store the dropped off bit
             */
-            (8, _, _, 6) => {
-                let x = d2 as usize;
-                let lsb = self.v_reg[x] & 1;
-                self.v_reg[x] >>= 1;
+            Instruction::ShrVxVy(x, y) => {
+                let x = x as usize;
+                let src = if self.quirks.shift_vx { x } else { y as usize };
+                let lsb = self.v_reg[src] & 1;
+                self.v_reg[x] = self.v_reg[src] >> 1;
                 self.v_reg[0xF] = lsb;
             }
 
             /*
                 8XY7
                 Subtract Registers with Overflow Instruction
-                Sets V[d2] = V[d3] - V[d2]
+                Sets V[X] = V[Y] - V[X]
                 The last register (V[15]) is used the flag bit
             */
-            (8, _, _, 7) => {
-                let x = d2 as usize;
-                let y = d3 as usize;
+            Instruction::SubnVxVy(x, y) => {
+                let x = x as usize;
+                let y = y as usize;
 
                 let (diff, borrow) = self.v_reg[y].overflowing_sub(self.v_reg[x]);
                 let borrow = if borrow { 0 } else { 1 };
@@ -359,27 +789,27 @@ impl CPU {
 
             /*
                 8XYE
-                Right Shift in Register Instruction
-                Sets V[d2] = V[d2] >> 1
+                Left Shift in Register Instruction
+                Sets V[X] = V[X] << 1
+                Without the shift quirk, V[Y] is shifted instead, that is V[X] = V[Y] << 1
                 The last register (V[15]) is used the flag bit, which is used to store if there was an overflow
             */
-            (8, _, _, 0xE) => {
-                let x = d2 as usize;
-                let msb = (self.v_reg[x] >> 7) & 1;
-                self.v_reg[x] <<= 1;
+            Instruction::ShlVxVy(x, y) => {
+                let x = x as usize;
+                let src = if self.quirks.shift_vx { x } else { y as usize };
+                let msb = (self.v_reg[src] >> 7) & 1;
+                self.v_reg[x] = self.v_reg[src] << 1;
                 self.v_reg[0xF] = msb;
             }
 
             /*
                 9XYO
                 Skip If Not Equal Instruction
-                Skips the next instruction if register V[d2] != register V[d3]
+                Skips the next instruction if register V[X] != register V[Y]
             */
-            (9, _, _, 0) => {
-                let x = d2 as usize;
-                let y = d3 as usize;
-                if self.v_reg[x] != self.v_reg[y] {
-                    self.pc += 2;
+            Instruction::SneVxVy(x, y) => {
+                if self.v_reg[x as usize] != self.v_reg[y as usize] {
+                    self.skip_next();
                 }
             }
 
@@ -388,19 +818,23 @@ impl CPU {
                 Set IR Instruction
                 Used to set the value of the Instruction Register === nnn, which will act as a memory pointer to RAM
             */
-            (0xA, _, _, _) => {
-                let nnn = op & 0xFFF;
+            Instruction::LdI(nnn) => {
                 self.i_reg = nnn;
             }
 
             /*
                 BNNN
                 Increment Program Counter Instruction
-                Sets value of the program counter = Register V[0] + the value corresponding to d2,d3,d4 in the opcode
+                Sets value of the program counter = Register V[0] + NNN
+                With the jump quirk, this is BXNN instead and the register V[X] is used in place of V[0]
             */
-            (0xB, _, _, _) => {
-                let nnn = op & 0xFFF;
-                self.pc = (self.v_reg[0] as u16) + nnn;
+            Instruction::JpV0(nnn) => {
+                let reg = if self.quirks.jump_vx {
+                    (nnn >> 8) as usize
+                } else {
+                    0
+                };
+                self.pc = (self.v_reg[reg] as u16) + nnn;
             }
 
             /*
@@ -408,52 +842,101 @@ impl CPU {
                 Set Register to Random Value Instrcution (with some bits unset)
                 Sets register V[X] = A random value & NN
             */
-            (0xC, _, _, _) => {
-                let x = d2 as usize;
-                let nn = (op & 0xFF) as u8;
-                let rng: u8 = random();
-                self.v_reg[x] = rng & nn;
+            Instruction::Rnd(x, nn) => {
+                self.v_reg[x as usize] = self.rng.next_u8() & nn;
             }
 
             /*
                 DXYN
                 Draw Sprite Instruction
+                On SUPER-CHIP, DXY0 draws a 16x16 sprite, where each row is made up of 2 bytes
+                On XO-CHIP, the sprite is drawn on each selected plane, with the data of each plane following the previous one
+                With the display wait quirk, only one sprite can be drawn per frame, except in hi-res on SUPER-CHIP
+                With the clipping quirk, the parts of the sprite going off the screen are not drawn, instead of wrapping around
+                In hi-res on SUPER-CHIP, V[F] is set to the number of rows which collided or were clipped at the bottom
             */
-            (0xD, _, _, _) => {
+            Instruction::Drw(vx, vy, n) => {
+                // SUPER-CHIP 1.1 draws the hi-res sprites right away, without waiting for the interrupt
+                let schip_hires = self.hires && self.platform == Platform::SuperChip;
+
+                // Redo OP Code until the next frame starts
+                if self.quirks.display_wait && !schip_hires {
+                    if !self.vblank {
+                        self.pc = self.pc.wrapping_sub(2);
+                        return Ok(());
+                    }
+                    self.vblank = false;
+                }
+
+                let width = self.screen_width();
+                let height = self.screen_height();
+
                 // Get the (x, y) coords for our sprite
-                let x_coord = self.v_reg[d2 as usize] as u16;
-                let y_coord = self.v_reg[d3 as usize] as u16;
+                // The starting position always wraps around the screen
+                let x_coord = self.v_reg[vx as usize] as usize % width;
+                let y_coord = self.v_reg[vy as usize] as usize % height;
 
                 // The last digit determines how many rows does our sprite have in its height
-                let num_rows = d4;
+                let big_sprite = n == 0 && self.platform.is_super_chip();
+                let (num_rows, num_cols) = if big_sprite {
+                    (16, 16)
+                } else {
+                    (n as usize, 8)
+                };
+                let bytes_per_row = num_cols / 8;
 
-                // Keep track if any pixels were flipped
+                // Keep track if any pixels were flipped, and of the rows where it happened
                 let mut flipped = false;
+                let mut rows_hit = 0;
+
+                // The memory address our next row's data is stored at
+                let mut addr = self.i_reg as usize;
 
-                // Iterate over each row of our sprite
-                for y_line in 0..num_rows {
-                    // Determine which memory address our row's data is stored
-                    let addr = self.i_reg + y_line as u16;
-                    let pixels = self.ram[addr as usize];
-                    // Iterate over each column in our row
-                    for x_line in 0..8 {
-                        // Use a mask to fetch current pixel's bit. Only flip if a 1
-                        if (pixels & (0b1000_0000 >> x_line)) != 0 {
-                            // Sprites should wrap around screen, so apply modulo
-                            let x = (x_coord + x_line) as usize % SCREEN_WIDTH;
-                            let y = (y_coord + y_line) as usize % SCREEN_HEIGHT;
-
-                            // Get our pixel's index for our 1D screen array
-                            let idx = x + SCREEN_WIDTH * y;
-                            // Check if we're about to flip the pixel and set
-                            flipped |= self.screen[idx];
-                            self.screen[idx] ^= true;
+                // Iterate over each selected plane
+                for plane in [PLANE_1, PLANE_2] {
+                    if self.planes & plane == 0 {
+                        continue;
+                    }
+
+                    // Iterate over each row of our sprite
+                    for y_line in 0..num_rows {
+                        let clipped = self.quirks.clip_sprites && y_coord + y_line >= height;
+                        let mut collided = false;
+                        let mut pixels = 0u16;
+                        for _ in 0..bytes_per_row {
+                            pixels = (pixels << 8) | self.read(addr)? as u16;
+                            addr += 1;
                         }
+                        // Iterate over each column in our row
+                        for x_line in 0..num_cols {
+                            // Use a mask to fetch current pixel's bit. Only flip if a 1
+                            if (pixels & (1 << (num_cols - 1 - x_line))) != 0 {
+                                let x = x_coord + x_line;
+                                let y = y_coord + y_line;
+                                if self.quirks.clip_sprites && (x >= width || y >= height) {
+                                    continue;
+                                }
+
+                                // Sprites should wrap around screen, so apply modulo
+                                let x = x % width;
+                                let y = y % height;
+
+                                // Get our pixel's index for our 1D screen array
+                                let idx = x + width * y;
+                                // Check if we're about to flip the pixel and set
+                                collided |= self.screen[idx] & plane != 0;
+                                self.screen[idx] ^= plane;
+                            }
+                        }
+                        flipped |= collided;
+                        rows_hit += (collided || clipped) as u8;
                     }
                 }
 
                 // Populate VF register
-                if flipped {
+                if schip_hires {
+                    self.v_reg[0xF] = rows_hit;
+                } else if flipped {
                     self.v_reg[0xF] = 1;
                 } else {
                     self.v_reg[0xF] = 0;
@@ -465,12 +948,11 @@ impl CPU {
                 Skip if Key Pressed Instruction
                 Skips the next instruction if the Key stored in the V[X] register is pressed (true)
             */
-            (0xE, _, 9, 0xE) => {
-                let x = d2 as usize;
-                let vx = self.v_reg[x];
-                let key = self.keys[vx as usize];
+            Instruction::Skp(x) => {
+                let vx = self.v_reg[x as usize];
+                let key = self.keys[(vx & 0xF) as usize];
                 if key {
-                    self.pc += 2;
+                    self.skip_next();
                 }
             }
 
@@ -479,12 +961,41 @@ impl CPU {
                 Skip if Key Released Instruction
                 Skips the next instruction if the Key stored in the V[X] register is not pressed (false)
             */
-            (0xE, _, 0xA, 1) => {
-                let x = d2 as usize;
-                let vx = self.v_reg[x];
-                let key = self.keys[vx as usize];
+            Instruction::Sknp(x) => {
+                let vx = self.v_reg[x as usize];
+                let key = self.keys[(vx & 0xF) as usize];
                 if !key {
-                    self.pc += 2;
+                    self.skip_next();
+                }
+            }
+
+            /*
+                F000 NNNN
+                Set IR to Long Address Instruction (XO-CHIP)
+                This instruction is 4 bytes long, and sets IR to the 16 bit address stored in the 2 bytes after the OP Code
+            */
+            Instruction::LdILong => {
+                self.i_reg = self.fetch()?;
+            }
+
+            /*
+                FN01
+                Select Planes Instruction (XO-CHIP)
+                Selects the planes (given as a bitmask in N) that drawing, clearing and scrolling act on
+            */
+            Instruction::Plane(n) => {
+                self.planes = n & ALL_PLANES;
+            }
+
+            /*
+                F002
+                Load Audio Pattern Instruction (XO-CHIP)
+                Loads the 16 bytes pointed to by IR into the audio pattern buffer
+            */
+            Instruction::Audio => {
+                let i = self.i_reg as usize;
+                for idx in 0..AUDIO_PATTERN_SIZE {
+                    self.audio_pattern[idx] = self.read(i + idx)?;
                 }
             }
 
@@ -493,9 +1004,8 @@ impl CPU {
                 Access Delay Timer Instruction
                 Sets the value of the delay timer in the V[X] register
             */
-            (0xF, _, 0, 7) => {
-                let x = d2 as usize;
-                self.v_reg[x] = self.dt;
+            Instruction::LdVxDt(x) => {
+                self.v_reg[x as usize] = self.dt;
             }
 
             /*
@@ -506,12 +1016,11 @@ impl CPU {
                 Once a key is found, it is stored into VX.
                 If more than one key is currently being pressed, it takes the lowest indexed one.
             */
-            (0xF, _, 0, 0xA) => {
-                let x = d2 as usize;
+            Instruction::LdVxK(x) => {
                 let mut pressed = false;
                 for i in 0..self.keys.len() {
                     if self.keys[i] {
-                        self.v_reg[x] = i as u8;
+                        self.v_reg[x as usize] = i as u8;
                         pressed = true;
                         break;
                     }
@@ -519,7 +1028,7 @@ impl CPU {
 
                 if !pressed {
                     // Redo OP Code
-                    self.pc -= 2;
+                    self.pc = self.pc.wrapping_sub(2);
                 }
             }
 
@@ -528,9 +1037,8 @@ impl CPU {
                 Set Delay Timer Instruction
                 Used to set the value of the Delay Timer = Value of the register V[X]
             */
-            (0xF, _, 1, 5) => {
-                let x = d2 as usize;
-                self.dt = self.v_reg[x];
+            Instruction::LdDtVx(x) => {
+                self.dt = self.v_reg[x as usize];
             }
 
             /*
@@ -538,9 +1046,8 @@ impl CPU {
                 Set Sound Timer Instruction
                 Used to set the value of the Sound Timer = Value of the register V[X]
             */
-            (0xF, _, 1, 8) => {
-                let x = d2 as usize;
-                self.st = self.v_reg[x];
+            Instruction::LdStVx(x) => {
+                self.st = self.v_reg[x as usize];
             }
 
             /*
@@ -549,9 +1056,8 @@ impl CPU {
                 Increases the value of IR by the value in register V[X]
                 If overflow occurs, the register rolls back to 0
             */
-            (0xF, _, 1, 0xE) => {
-                let x = d2 as usize;
-                let vx = self.v_reg[x] as u16;
+            Instruction::AddIVx(x) => {
+                let vx = self.v_reg[x as usize] as u16;
                 self.i_reg = self.i_reg.wrapping_add(vx);
             }
 
@@ -560,20 +1066,28 @@ impl CPU {
                 Set IR to Font Address Instruction
                 Takes in the number to print on screen (from 0 to 0xF), and store the RAM address of that sprite into the IR We are actually free to store those sprites anywhere we wanted, so long as we are consistent and point to Convenient to implement as we stored all of them at the beginning of RAM.
             */
-            (0xF, _, 2, 9) => {
-                let x = d2 as usize;
-                let c = self.v_reg[x] as u16;
+            Instruction::LdFVx(x) => {
+                let c = self.v_reg[x as usize] as u16;
                 self.i_reg = c * 5;
             }
 
+            /*
+                FX30
+                Set IR to Big Font Address Instruction (SUPER-CHIP)
+                Same as FX29, but for the 10 rows tall sprites of the big FONTSET
+            */
+            Instruction::LdHfVx(x) => {
+                let c = (self.v_reg[x as usize] & 0xF) as u16;
+                self.i_reg = BIG_FONTSET_ADDR as u16 + c * 10;
+            }
+
             /*
                 FX33
                 Store BCD Instruction
                 Set IR = BCD of V[X]
             */
-            (0xF, _, 3, 3) => {
-                let x = d2 as usize;
-                let vx = self.v_reg[x] as f32;
+            Instruction::LdBVx(x) => {
+                let vx = self.v_reg[x as usize] as f32;
 
                 // Fetch the hundreds digit by dividing by 100 and tossing the decimal
                 let hundreds = (vx / 100.0).floor() as u8;
@@ -582,21 +1096,35 @@ impl CPU {
                 // Fetch the ones digit by tossing the hundreds and the tens
                 let ones = (vx % 10.0) as u8;
 
-                self.ram[self.i_reg as usize] = hundreds;
-                self.ram[(self.i_reg + 1) as usize] = tens;
-                self.ram[(self.i_reg + 2) as usize] = ones;
+                let i = self.i_reg as usize;
+                self.write(i, hundreds)?;
+                self.write(i + 1, tens)?;
+                self.write(i + 2, ones)?;
+            }
+
+            /*
+                FX3A
+                Set Pitch Instruction (XO-CHIP)
+                Sets the playback rate of the audio pattern buffer to the value of the register V[X]
+            */
+            Instruction::Pitch(x) => {
+                self.pitch = self.v_reg[x as usize];
             }
 
             /*
                 FX55
                 Store V register's into RAM Instrcution
                 Stores the value of all registers from V[0] to V[X] in RAM, starting from the adress pointed by IR
+                With the memory quirk, IR is left pointing after the last stored register
             */
-            (0xF, _, 5, 5) => {
-                let x = d2 as usize;
+            Instruction::LdIVx(x) => {
+                let x = x as usize;
                 let i = self.i_reg as usize;
                 for idx in 0..=x {
-                    self.ram[i + idx] = self.v_reg[idx];
+                    self.write(i + idx, self.v_reg[idx])?;
+                }
+                if self.quirks.increment_i {
+                    self.i_reg = self.i_reg.wrapping_add(x as u16 + 1);
                 }
             }
 
@@ -604,38 +1132,244 @@ impl CPU {
                 FX65
                 Load V register's from RAM Instrcution
                 Loads the value into all registers from V[0] to V[X] from RAM, starting from the adress pointed by IR
+                With the memory quirk, IR is left pointing after the last loaded register
             */
-            (0xF, _, 6, 5) => {
-                let x = d2 as usize;
+            Instruction::LdVxI(x) => {
+                let x = x as usize;
                 let i = self.i_reg as usize;
                 for idx in 0..=x {
-                    self.v_reg[idx] = self.ram[i + idx];
+                    self.v_reg[idx] = self.read(i + idx)?;
                 }
+                if self.quirks.increment_i {
+                    self.i_reg = self.i_reg.wrapping_add(x as u16 + 1);
+                }
+            }
+
+            /*
+                FX75
+                Store V register's into RPL User Flags Instruction (SUPER-CHIP)
+                Stores the value of all registers from V[0] to V[X] in the RPL user flags
+            */
+            Instruction::LdRVx(x) => {
+                let x = x as usize;
+                self.rpl[..=x].copy_from_slice(&self.v_reg[..=x]);
+            }
+
+            /*
+                FX85
+                Load V register's from RPL User Flags Instruction (SUPER-CHIP)
+                Loads the value into all registers from V[0] to V[X] from the RPL user flags
+            */
+            Instruction::LdVxR(x) => {
+                let x = x as usize;
+                self.v_reg[..=x].copy_from_slice(&self.rpl[..=x]);
             }
+        }
+
+        Ok(())
+    }
+}
+
+// Read-only access to the registers and the RAM, for the tools built around the emulator
+impl CPU {
+    pub fn pc(&self) -> u16 {
+        self.pc
+    }
+
+    pub fn i_reg(&self) -> u16 {
+        self.i_reg
+    }
 
-            // Match all the left cases that have not been handled yet
-            (_, _, _, _) => unimplemented!("Unimplemented OP Code: {}", op),
+    pub fn v_reg(&self) -> &[u8; NUM_REGS] {
+        &self.v_reg
+    }
+
+    pub fn sp(&self) -> u16 {
+        self.sp
+    }
+
+    // The return addresses currently on the stack, the latest one last
+    pub fn stack(&self) -> &[u16] {
+        &self.stack[..self.sp as usize]
+    }
+
+    pub fn dt(&self) -> u8 {
+        self.dt
+    }
+
+    pub fn st(&self) -> u8 {
+        self.st
+    }
+
+    pub fn keys(&self) -> &[bool; NUM_KEYS] {
+        &self.keys
+    }
+
+    // The XO-CHIP audio pattern, 128 bits played one after the other while the sound timer is active
+    pub fn audio_pattern(&self) -> &[u8; AUDIO_PATTERN_SIZE] {
+        &self.audio_pattern
+    }
+
+    // The XO-CHIP pitch, which sets the rate the audio pattern is played at
+    pub fn pitch(&self) -> u8 {
+        self.pitch
+    }
+
+    // All the registers at once, to be kept around or compared with another state
+    pub fn cpu_state(&self) -> CpuState {
+        CpuState {
+            pc: self.pc,
+            i_reg: self.i_reg,
+            v_reg: self.v_reg,
+            stack: self.stack().to_vec(),
+            dt: self.dt,
+            st: self.st,
+            keys: self.keys,
+            hires: self.hires,
+            planes: self.planes,
+            halted: self.halted,
         }
     }
+
+    // The byte at the address, or None when the address is past the end of the RAM
+    pub fn peek(&self, addr: usize) -> Option<u8> {
+        self.ram.get(addr).copied()
+    }
+
+    // The whole RAM, which is 4 KB in size (64 KB on XO-CHIP)
+    pub fn memory(&self) -> &[u8] {
+        &self.ram
+    }
+}
+
+// Changes to the registers and the RAM, for the debuggers
+// They keep the CPU in a state it could have reached on its own, so they can't fail in the middle of a program
+impl CPU {
+    // The PC is wrapped to the RAM
+    pub fn set_pc(&mut self, pc: u16) {
+        self.pc = (pc as usize % self.ram.len()) as u16;
+    }
+
+    pub fn set_i_reg(&mut self, i: u16) {
+        self.i_reg = i;
+    }
+
+    // Only the lowest digit of the index is used, so V[16] is V[0]
+    pub fn set_v_reg(&mut self, x: usize, value: u8) {
+        self.v_reg[x % NUM_REGS] = value;
+    }
+
+    pub fn set_dt(&mut self, dt: u8) {
+        self.dt = dt;
+    }
+
+    pub fn set_st(&mut self, st: u8) {
+        self.st = st;
+    }
+
+    // Fails when the address is past the end of the RAM
+    // Unlike the writes of the program, it doesn't hit the watchpoints
+    pub fn write_memory(&mut self, addr: usize, value: u8) -> Result<(), Chip8Error> {
+        let cell = self
+            .ram
+            .get_mut(addr)
+            .ok_or(Chip8Error::MemoryOutOfBounds { addr })?;
+        *cell = value;
+        Ok(())
+    }
+}
+
+// Tracing, to compare the instructions run with the ones of another emulator
+// The trace is kept when the CPU is reset, and it isn't part of the save states
+impl CPU {
+    // Start tracing every instruction run from now on, replacing the previous trace
+    pub fn set_trace(&mut self, trace: Trace) {
+        self.trace = Some(trace);
+    }
+
+    pub fn trace(&self) -> Option<&Trace> {
+        self.trace.as_ref()
+    }
+
+    // Stop tracing, and get the trace back to read its lines or flush it
+    pub fn take_trace(&mut self) -> Option<Trace> {
+        self.trace.take()
+    }
+}
+
+// Watchpoints, to find out which instruction reads or changes the RAM or a register
+// They are kept when the CPU is reset, and they aren't part of the save states
+impl CPU {
+    // Returns the id of the watchpoint, to be given to `remove_watchpoint` and found in its hits
+    pub fn add_watchpoint(&mut self, target: WatchTarget, action: WatchAction) -> usize {
+        self.watchpoints.add(target, action)
+    }
+
+    // Returns false when there is no watchpoint with this id
+    pub fn remove_watchpoint(&mut self, id: usize) -> bool {
+        self.watchpoints.remove(id)
+    }
+
+    pub fn clear_watchpoints(&mut self) {
+        self.watchpoints.clear();
+    }
+
+    // The hit of a `WatchAction::Halt` watchpoint which stopped the program, if any
+    // The program resumes on the next tick once the hit has been taken
+    pub fn take_watch_hit(&mut self) -> Option<WatchHit> {
+        self.watchpoints.hit.take()
+    }
 }
 
 // Implementations to deal with the interaction with the frontend
 impl CPU {
-    // Send the screen to the frontend
-    pub fn get_display(&self) -> &[bool] {
-        &self.screen
+    // Send the screen to the frontend, along with the resolution it is currently in
+    pub fn get_display(&self) -> Display<'_> {
+        Display {
+            width: self.screen_width(),
+            height: self.screen_height(),
+            pixels: &self.screen,
+        }
+    }
+
+    // Whether the buzzer is sounding, which the frontend plays as a tone
+    pub fn is_sound_active(&self) -> bool {
+        self.st > 0
     }
 
     // Handle key-presses and store them in the CPU
     // The frontend would map the actual key-presses to the CPU keys
+    // Keys past F don't exist on the keypad, so they are ignored
     pub fn keypress(&mut self, idx: usize, pressed: bool) {
-        self.keys[idx] = pressed;
+        if let Some(key) = self.keys.get_mut(idx) {
+            *key = pressed;
+        }
     }
 
     // Copy the contents sent to the RAM of the CPU
-    pub fn load(&mut self, data: &[u8]) {
+    // The RAM is left untouched if they don't fit in it, after the interpreter area
+    pub fn load(&mut self, data: &[u8]) -> Result<(), Chip8Error> {
         let start = START_ADDR as usize;
-        let end = (START_ADDR as usize) + data.len();
-        self.ram[start..end].copy_from_slice(data);
+        let max = self.ram.len() - start;
+        if data.len() > max {
+            return Err(Chip8Error::RomTooLarge {
+                size: data.len(),
+                max,
+            });
+        }
+        self.ram[start..start + data.len()].copy_from_slice(data);
+        self.rom_hash = config::hash_rom(data);
+        Ok(())
     }
+
+    // The SHA-1 of the loaded ROM, which identifies the game being played
+    pub fn rom_hash(&self) -> [u8; 20] {
+        self.rom_hash
+    }
+}
+
+// The registers from V[x] to V[y], in reverse order if x > y
+fn register_range(x: usize, y: usize) -> impl Iterator<Item = usize> {
+    let (lo, hi) = (x.min(y), x.max(y));
+    (lo..=hi).map(move |reg| if x <= y { reg } else { lo + hi - reg })
 }
@@ -0,0 +1,204 @@
+use crate::state::{platform_from_id, platform_id, Reader};
+use crate::{config, Platform, Quirks, StateError, CPU, NUM_KEYS};
+
+// MOVIE FORMAT
+// Every movie starts with the magic header, followed by the version of the format
+// All the multi-byte values are stored in big endian, like the save states
+const MAGIC: [u8; 4] = *b"C8MV";
+const VERSION: u8 = 2;
+
+// A key being pressed or released, at the start of the given frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyEvent {
+    pub frame: u32,
+    pub key: u8,
+    pub pressed: bool,
+}
+
+// A recording of a play session, which can be played back identically
+//
+// Along with the key presses of every frame, a movie holds everything the run depends on: the ROM (through its hash),
+// the platform, the quirks, the seed of the random number generator and the instructions run per second, as the key
+// presses land on other instructions at another speed
+// Both when recording and when playing back, `start` must be used to set the CPU up before running the first frame
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Movie {
+    pub seed: u64,
+    pub platform: Platform,
+    pub quirks: Quirks,
+    pub rom_hash: [u8; 20],
+    pub ips: u32,
+    frames: u32,
+    events: Vec<KeyEvent>,
+}
+
+impl Movie {
+    // An empty movie, to record a session of the given ROM
+    // The frontend must run `ips` instructions per second, both when recording and when playing back
+    pub fn new(rom: &[u8], platform: Platform, quirks: Quirks, seed: u64, ips: u32) -> Self {
+        Movie {
+            seed,
+            platform,
+            quirks,
+            rom_hash: config::hash_rom(rom),
+            ips,
+            frames: 0,
+            events: Vec::new(),
+        }
+    }
+
+    // Number of frames recorded
+    pub fn frames(&self) -> u32 {
+        self.frames
+    }
+
+    pub fn events(&self) -> &[KeyEvent] {
+        &self.events
+    }
+
+    // Set the CPU up for the first frame of the movie, by loading the ROM on a freshly reset CPU
+    // Fails if the ROM isn't the one the movie was recorded with
+    pub fn start(&self, cpu: &mut CPU, rom: &[u8]) -> Result<(), StateError> {
+        let mut new_cpu = CPU::with_quirks(self.quirks);
+        new_cpu.set_platform(self.platform);
+        new_cpu.set_seed(self.seed);
+        // The ROM was loaded on this platform while recording, so the movie is corrupted if it doesn't fit anymore
+        new_cpu.load(rom).map_err(|_| StateError::Corrupted)?;
+        if new_cpu.rom_hash() != self.rom_hash {
+            return Err(StateError::RomMismatch);
+        }
+        *cpu = new_cpu;
+        Ok(())
+    }
+
+    // RECORDING
+    // Called for every key press of the current frame, which must be passed on to the CPU as well
+    pub fn record(&mut self, key: usize, pressed: bool) {
+        self.events.push(KeyEvent {
+            frame: self.frames,
+            key: key as u8,
+            pressed,
+        });
+    }
+
+    // Called once the current frame has been run, so that the next key presses are recorded for the next frame
+    pub fn end_frame(&mut self) {
+        self.frames += 1;
+    }
+
+    // PLAYBACK
+    // Send the key presses of the given frame to the CPU, before running the frame
+    // Returns false once the movie is over
+    pub fn play_frame(&self, frame: u32, cpu: &mut CPU) -> bool {
+        let first = self.events.partition_point(|event| event.frame < frame);
+        for event in self.events[first..]
+            .iter()
+            .take_while(|event| event.frame == frame)
+        {
+            cpu.keypress(event.key as usize, event.pressed);
+        }
+        frame < self.frames
+    }
+
+    // SERIALIZATION
+    // The key presses recorded after the last frame ended (when the session is closed while paused, for instance)
+    // never reached a frame, so they are left out
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let events = &self.events[..self
+            .events
+            .partition_point(|event| event.frame < self.frames)];
+        let mut data = Vec::with_capacity(52 + events.len() * 6);
+        data.extend_from_slice(&MAGIC);
+        data.push(VERSION);
+        data.extend_from_slice(&self.rom_hash);
+        data.push(platform_id(self.platform));
+        data.push(quirks_to_bits(self.quirks));
+        data.extend_from_slice(&self.seed.to_be_bytes());
+        data.extend_from_slice(&self.ips.to_be_bytes());
+        data.extend_from_slice(&self.frames.to_be_bytes());
+        data.extend_from_slice(&(events.len() as u32).to_be_bytes());
+        for event in events {
+            data.extend_from_slice(&event.frame.to_be_bytes());
+            data.push(event.key);
+            data.push(event.pressed as u8);
+        }
+        data
+    }
+
+    pub fn from_bytes(data: &[u8]) -> Result<Self, StateError> {
+        let mut reader = Reader { data };
+        if reader.array::<4>()? != MAGIC {
+            return Err(StateError::BadMagic);
+        }
+        let version = reader.u8()?;
+        if version != VERSION {
+            return Err(StateError::UnsupportedVersion { version });
+        }
+        let rom_hash = reader.array()?;
+        let platform = platform_from_id(reader.u8()?).ok_or(StateError::Corrupted)?;
+        let quirks = quirks_from_bits(reader.u8()?);
+        let seed = u64::from_be_bytes(reader.array()?);
+        let ips = reader.u32()?;
+        if ips == 0 {
+            return Err(StateError::Corrupted);
+        }
+        let frames = reader.u32()?;
+
+        // The events must be in order, and within the movie
+        let mut events = Vec::new();
+        for _ in 0..reader.u32()? {
+            let event = KeyEvent {
+                frame: reader.u32()?,
+                key: reader.u8()?,
+                pressed: reader.bool()?,
+            };
+            let in_order = events
+                .last()
+                .is_none_or(|last: &KeyEvent| last.frame <= event.frame);
+            if !in_order || event.frame >= frames || event.key as usize >= NUM_KEYS {
+                return Err(StateError::Corrupted);
+            }
+            events.push(event);
+        }
+        if !reader.data.is_empty() {
+            return Err(StateError::Corrupted);
+        }
+
+        Ok(Movie {
+            seed,
+            platform,
+            quirks,
+            rom_hash,
+            ips,
+            frames,
+            events,
+        })
+    }
+}
+
+// The quirks are stored as a single byte, one bit per quirk
+fn quirks_to_bits(quirks: Quirks) -> u8 {
+    [
+        quirks.shift_vx,
+        quirks.increment_i,
+        quirks.jump_vx,
+        quirks.vf_reset,
+        quirks.clip_sprites,
+        quirks.display_wait,
+    ]
+    .iter()
+    .enumerate()
+    .fold(0, |bits, (bit, &set)| bits | ((set as u8) << bit))
+}
+
+fn quirks_from_bits(bits: u8) -> Quirks {
+    let bit = |idx: u8| bits & (1 << idx) != 0;
+    Quirks {
+        shift_vx: bit(0),
+        increment_i: bit(1),
+        jump_vx: bit(2),
+        vf_reset: bit(3),
+        clip_sprites: bit(4),
+        display_wait: bit(5),
+    }
+}
@@ -0,0 +1,81 @@
+use crate::{Quirks, RAM_SIZE, XO_RAM_SIZE};
+use std::fmt;
+use std::str::FromStr;
+
+// The flavours of CHIP-8 that the CPU can run
+// Each of them extends the instruction set of the previous one
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Platform {
+    // The original CHIP-8, with a 64x32 display
+    #[default]
+    Chip8,
+    // SUPER-CHIP 1.1, which adds the 128x64 hi-res mode, scrolling, big fonts and the RPL user flags
+    SuperChip,
+    // XO-CHIP, which adds 64 KB of RAM, a second bitplane for 4 colors and programmable audio
+    XoChip,
+}
+
+impl Platform {
+    // The quirks the programs written for this platform usually expect
+    // Most CHIP-8 programs in circulation were written or fixed up for CHIP-48 and the emulators which followed it, and
+    // run the same with the modern quirks, while the display wait of the COSMAC VIP slows them down, so it is left to
+    // the ROMs known to need it (see database.rs) or to the `vip` preset
+    pub fn quirks(&self) -> Quirks {
+        match self {
+            Platform::Chip8 => Quirks::MODERN,
+            Platform::SuperChip => Quirks::SUPER_CHIP,
+            Platform::XoChip => Quirks::XO_CHIP,
+        }
+    }
+
+    // Whether the SUPER-CHIP instructions can be executed
+    pub fn is_super_chip(&self) -> bool {
+        *self != Platform::Chip8
+    }
+
+    // Whether the XO-CHIP instructions can be executed
+    pub fn is_xo_chip(&self) -> bool {
+        *self == Platform::XoChip
+    }
+
+    // Whether the instruction set of this platform includes the one of the given platform
+    pub fn supports(&self, other: Platform) -> bool {
+        match other {
+            Platform::Chip8 => true,
+            Platform::SuperChip => self.is_super_chip(),
+            Platform::XoChip => self.is_xo_chip(),
+        }
+    }
+
+    // Size of the RAM available to the programs, in bytes
+    pub fn ram_size(&self) -> usize {
+        if self.is_xo_chip() {
+            XO_RAM_SIZE
+        } else {
+            RAM_SIZE
+        }
+    }
+}
+
+impl FromStr for Platform {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "chip8" | "chip-8" => Ok(Platform::Chip8),
+            "schip" | "superchip" | "super-chip" => Ok(Platform::SuperChip),
+            "xochip" | "xo-chip" => Ok(Platform::XoChip),
+            _ => Err(format!("unknown platform: {}", s)),
+        }
+    }
+}
+
+impl fmt::Display for Platform {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Platform::Chip8 => write!(f, "chip8"),
+            Platform::SuperChip => write!(f, "schip"),
+            Platform::XoChip => write!(f, "xochip"),
+        }
+    }
+}
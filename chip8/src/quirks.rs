@@ -0,0 +1,95 @@
+use std::str::FromStr;
+
+// Behaviours of the ambiguous instructions, which differ between the interpreters that CHIP-8 programs were written for
+// Pick the preset matching the interpreter a ROM was written for, or toggle each quirk individually
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Quirks {
+    // 8XY6 and 8XYE shift V[X] in place (true), instead of shifting V[Y] and storing the result in V[X] (false)
+    pub shift_vx: bool,
+    // FX55 and FX65 leave I pointing after the last register stored or loaded
+    pub increment_i: bool,
+    // BNNN is treated as BXNN and jumps to V[X] + NN, instead of V[0] + NNN
+    pub jump_vx: bool,
+    // 8XY1, 8XY2 and 8XY3 reset V[F] to 0
+    pub vf_reset: bool,
+    // DXYN clips the sprites at the edges of the screen, instead of wrapping them around to the other side
+    pub clip_sprites: bool,
+    // DXYN waits for the next frame (the vertical blank interrupt) before drawing
+    pub display_wait: bool,
+}
+
+impl Quirks {
+    // The original interpreter on the COSMAC VIP
+    pub const COSMAC_VIP: Quirks = Quirks {
+        shift_vx: false,
+        increment_i: true,
+        jump_vx: false,
+        vf_reset: true,
+        clip_sprites: true,
+        display_wait: true,
+    };
+
+    // The CHIP-48 interpreter for the HP-48 calculators, which have no vertical blank interrupt to wait for
+    pub const CHIP_48: Quirks = Quirks {
+        shift_vx: true,
+        increment_i: false,
+        jump_vx: true,
+        vf_reset: false,
+        clip_sprites: true,
+        display_wait: false,
+    };
+
+    // SUPER-CHIP 1.1, the successor of CHIP-48
+    // It waits for the interrupt before drawing in lo-res, but not in hi-res, where it also counts the rows which
+    // collided or were clipped at the bottom of the screen in V[F] (see DXYN in lib.rs)
+    pub const SUPER_CHIP: Quirks = Quirks {
+        shift_vx: true,
+        increment_i: false,
+        jump_vx: true,
+        vf_reset: false,
+        clip_sprites: true,
+        display_wait: true,
+    };
+
+    // XO-CHIP, as implemented by Octo
+    pub const XO_CHIP: Quirks = Quirks {
+        shift_vx: false,
+        increment_i: true,
+        jump_vx: false,
+        vf_reset: false,
+        clip_sprites: false,
+        display_wait: false,
+    };
+
+    // The behaviour most modern emulators (and this one, by default) settled on
+    pub const MODERN: Quirks = Quirks {
+        shift_vx: true,
+        increment_i: false,
+        jump_vx: false,
+        vf_reset: false,
+        clip_sprites: false,
+        display_wait: false,
+    };
+}
+
+impl Default for Quirks {
+    fn default() -> Self {
+        Quirks::MODERN
+    }
+}
+
+// The presets by name, such as `vip` or `schip`
+impl FromStr for Quirks {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "vip" | "cosmac" | "cosmac-vip" => Ok(Quirks::COSMAC_VIP),
+            "chip48" | "chip-48" => Ok(Quirks::CHIP_48),
+            "schip" | "superchip" | "super-chip" => Ok(Quirks::SUPER_CHIP),
+            "xochip" | "xo-chip" => Ok(Quirks::XO_CHIP),
+            "modern" => Ok(Quirks::MODERN),
+            _ => Err(format!("unknown quirks: {}", s)),
+        }
+    }
+}
@@ -0,0 +1,164 @@
+use crate::{StateError, CPU};
+use std::collections::VecDeque;
+
+// Records the recent history of a CPU, so that the program can be played backwards
+//
+// A snapshot is taken every `interval` frames, and up to `capacity` of them are kept, the oldest ones being dropped first
+// Only the latest snapshot is stored in full: every older one is stored as the difference with the snapshot after it,
+// which is tiny since only a few bytes of the RAM and the screen change from one frame to the next
+pub struct Rewind {
+    capacity: usize,
+    interval: usize,
+    frames: usize,             // Frames since the last snapshot was taken
+    latest: Option<Vec<u8>>,   // Latest snapshot, in full
+    deltas: VecDeque<Vec<u8>>, // Each delta turns a snapshot into the one before it, the oldest one at the front
+}
+
+impl Rewind {
+    // The capacity and interval are at least 1
+    pub fn new(capacity: usize, interval: usize) -> Self {
+        Rewind {
+            capacity: capacity.max(1),
+            interval: interval.max(1),
+            frames: 0,
+            latest: None,
+            deltas: VecDeque::new(),
+        }
+    }
+
+    // Number of snapshots which can be stepped back to
+    pub fn len(&self) -> usize {
+        match self.latest {
+            Some(_) => self.deltas.len() + 1,
+            None => 0,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.latest.is_none()
+    }
+
+    // Forget every snapshot, for instance after loading another game
+    pub fn clear(&mut self) {
+        self.frames = 0;
+        self.latest = None;
+        self.deltas.clear();
+    }
+
+    // Called once per frame, to take a snapshot of the CPU every `interval` frames
+    pub fn record(&mut self, cpu: &CPU) {
+        self.frames += 1;
+        if self.frames < self.interval && self.latest.is_some() {
+            return;
+        }
+        self.frames = 0;
+
+        let state = cpu.save_state();
+        if let Some(latest) = self.latest.take() {
+            self.deltas.push_back(diff(&state, &latest));
+            if self.deltas.len() >= self.capacity {
+                self.deltas.pop_front();
+            }
+        }
+        self.latest = Some(state);
+    }
+
+    // Restore the CPU to the latest snapshot, and drop it so that the next call goes one snapshot further back
+    // The snapshot of the current state, taken at the end of the last frame, is skipped as restoring it changes nothing
+    // Returns false once there is nothing left to rewind to
+    pub fn step_back(&mut self, cpu: &mut CPU) -> Result<bool, StateError> {
+        if self.latest.as_deref() == Some(cpu.save_state().as_slice()) {
+            self.pop();
+        }
+        let state = match self.pop() {
+            Some(state) => state,
+            None => return Ok(false),
+        };
+        cpu.load_state(&state)?;
+        self.frames = 0;
+        Ok(true)
+    }
+
+    // Drop the latest snapshot, and rebuild the one before it
+    fn pop(&mut self) -> Option<Vec<u8>> {
+        let state = self.latest.take()?;
+        self.latest = self.deltas.pop_back().map(|delta| patch(&state, &delta));
+        Some(state)
+    }
+}
+
+// DELTA COMPRESSION
+// A delta holds the length of the target, followed by runs of (unchanged bytes, changed bytes, XOR of the changed bytes)
+// The shorter of the two snapshots is padded with zeros, as their sizes differ when the resolution changes
+// All the lengths are stored as LEB128 variable length integers
+
+// The delta which turns `from` into `to`
+pub(crate) fn diff(from: &[u8], to: &[u8]) -> Vec<u8> {
+    let byte_at = |data: &[u8], idx: usize| data.get(idx).copied().unwrap_or(0);
+    let len = from.len().max(to.len());
+    let mut delta = Vec::new();
+    push_len(&mut delta, to.len());
+
+    let mut idx = 0;
+    while idx < len {
+        let unchanged = idx;
+        while idx < len && byte_at(from, idx) == byte_at(to, idx) {
+            idx += 1;
+        }
+        if idx == len {
+            break;
+        }
+        let changed = idx;
+        while idx < len && byte_at(from, idx) != byte_at(to, idx) {
+            idx += 1;
+        }
+        push_len(&mut delta, changed - unchanged);
+        push_len(&mut delta, idx - changed);
+        delta.extend((changed..idx).map(|i| byte_at(from, i) ^ byte_at(to, i)));
+    }
+    delta
+}
+
+// Apply a delta made by `diff` to the snapshot it was made from
+pub(crate) fn patch(from: &[u8], delta: &[u8]) -> Vec<u8> {
+    let mut pos = 0;
+    let len = read_len(delta, &mut pos);
+    let mut to = from.to_vec();
+    let mut idx = 0;
+    while pos < delta.len() {
+        idx += read_len(delta, &mut pos);
+        let changed = read_len(delta, &mut pos);
+        if to.len() < idx + changed {
+            to.resize(idx + changed, 0);
+        }
+        for (byte, xor) in to[idx..idx + changed].iter_mut().zip(&delta[pos..]) {
+            *byte ^= xor;
+        }
+        idx += changed;
+        pos += changed;
+    }
+    to.resize(len, 0);
+    to
+}
+
+fn push_len(delta: &mut Vec<u8>, mut len: usize) {
+    while len >= 0x80 {
+        delta.push((len as u8 & 0x7F) | 0x80);
+        len >>= 7;
+    }
+    delta.push(len as u8);
+}
+
+fn read_len(delta: &[u8], pos: &mut usize) -> usize {
+    let mut len = 0;
+    let mut shift = 0;
+    while let Some(&byte) = delta.get(*pos) {
+        *pos += 1;
+        len |= ((byte & 0x7F) as usize) << shift;
+        if byte & 0x80 == 0 {
+            break;
+        }
+        shift += 7;
+    }
+    len
+}
@@ -0,0 +1,184 @@
+use crate::{
+    Platform, Rng, StateError, CPU, HIRES_SCREEN_HEIGHT, HIRES_SCREEN_WIDTH, NUM_KEYS, NUM_REGS,
+    SCREEN_HEIGHT, SCREEN_WIDTH, STACK_SIZE,
+};
+
+// SAVE STATE FORMAT
+// Every save state starts with the magic header, followed by the version of the format
+// All the multi-byte values are stored in big endian, like the OP Codes
+const MAGIC: [u8; 4] = *b"C8ST";
+const VERSION: u8 = 2;
+
+// Reads the fields of a save state (or of a movie) one after the other
+pub(crate) struct Reader<'a> {
+    pub(crate) data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    pub(crate) fn bytes(&mut self, len: usize) -> Result<&'a [u8], StateError> {
+        if self.data.len() < len {
+            return Err(StateError::Truncated);
+        }
+        let (bytes, rest) = self.data.split_at(len);
+        self.data = rest;
+        Ok(bytes)
+    }
+
+    pub(crate) fn array<const N: usize>(&mut self) -> Result<[u8; N], StateError> {
+        let mut array = [0; N];
+        array.copy_from_slice(self.bytes(N)?);
+        Ok(array)
+    }
+
+    pub(crate) fn u8(&mut self) -> Result<u8, StateError> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    pub(crate) fn u16(&mut self) -> Result<u16, StateError> {
+        Ok(u16::from_be_bytes(self.array()?))
+    }
+
+    pub(crate) fn u32(&mut self) -> Result<u32, StateError> {
+        Ok(u32::from_be_bytes(self.array()?))
+    }
+
+    pub(crate) fn bool(&mut self) -> Result<bool, StateError> {
+        match self.u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(StateError::Corrupted),
+        }
+    }
+}
+
+// Deals with saving the state of the CPU, and restoring it later on
+impl CPU {
+    // Serialize the whole state of the CPU, so that the program can be resumed later on with `load_state`
+    // The quirks are not part of the state, as they are a setting of the frontend
+    pub fn save_state(&self) -> Vec<u8> {
+        let mut data = Vec::with_capacity(self.ram.len() + self.screen.len() + 256);
+        data.extend_from_slice(&MAGIC);
+        data.push(VERSION);
+        data.extend_from_slice(&self.rom_hash);
+        data.push(platform_id(self.platform));
+
+        data.extend_from_slice(&self.pc.to_be_bytes());
+        data.extend_from_slice(&self.i_reg.to_be_bytes());
+        data.extend_from_slice(&self.sp.to_be_bytes());
+        data.extend_from_slice(&self.v_reg);
+        for addr in self.stack {
+            data.extend_from_slice(&addr.to_be_bytes());
+        }
+        data.extend(self.keys.map(|pressed| pressed as u8));
+        data.push(self.dt);
+        data.push(self.st);
+
+        data.push(self.hires as u8);
+        data.push(self.planes);
+        data.push(self.halted as u8);
+        data.extend_from_slice(&self.rpl);
+        data.extend_from_slice(&self.audio_pattern);
+        data.push(self.pitch);
+        data.extend_from_slice(&self.rng.state().to_be_bytes());
+
+        data.extend_from_slice(&(self.ram.len() as u32).to_be_bytes());
+        data.extend_from_slice(&self.ram);
+        data.extend_from_slice(&self.screen);
+        data
+    }
+
+    // Restore a state serialized by `save_state`
+    // The state is only accepted if it was saved while running the same ROM as the one currently loaded
+    pub fn load_state(&mut self, data: &[u8]) -> Result<(), StateError> {
+        let mut reader = Reader { data };
+        if reader.array::<4>()? != MAGIC {
+            return Err(StateError::BadMagic);
+        }
+        let version = reader.u8()?;
+        if version != VERSION {
+            return Err(StateError::UnsupportedVersion { version });
+        }
+        if reader.array::<20>()? != self.rom_hash {
+            return Err(StateError::RomMismatch);
+        }
+        let platform = platform_from_id(reader.u8()?).ok_or(StateError::Corrupted)?;
+
+        let pc = reader.u16()?;
+        let i_reg = reader.u16()?;
+        let sp = reader.u16()?;
+        let v_reg: [u8; NUM_REGS] = reader.array()?;
+        let mut stack = [0; STACK_SIZE];
+        for addr in stack.iter_mut() {
+            *addr = reader.u16()?;
+        }
+        let mut keys = [false; NUM_KEYS];
+        for pressed in keys.iter_mut() {
+            *pressed = reader.bool()?;
+        }
+        let dt = reader.u8()?;
+        let st = reader.u8()?;
+
+        let hires = reader.bool()?;
+        let planes = reader.u8()?;
+        let halted = reader.bool()?;
+        let rpl = reader.array()?;
+        let audio_pattern = reader.array()?;
+        let pitch = reader.u8()?;
+        let rng = Rng::new(u64::from_be_bytes(reader.array()?));
+
+        // The sizes of the RAM and the screen must match the platform and the resolution
+        let ram_size = reader.u32()? as usize;
+        let screen_size = if hires {
+            HIRES_SCREEN_WIDTH * HIRES_SCREEN_HEIGHT
+        } else {
+            SCREEN_WIDTH * SCREEN_HEIGHT
+        };
+        if ram_size != platform.ram_size() || sp as usize > STACK_SIZE || planes > 3 {
+            return Err(StateError::Corrupted);
+        }
+        let ram = reader.bytes(ram_size)?;
+        let screen = reader.bytes(screen_size)?;
+        if !reader.data.is_empty() || screen.iter().any(|&pixel| pixel > 3) {
+            return Err(StateError::Corrupted);
+        }
+
+        self.platform = platform;
+        self.pc = pc;
+        self.i_reg = i_reg;
+        self.sp = sp;
+        self.v_reg = v_reg;
+        self.stack = stack;
+        self.keys = keys;
+        self.dt = dt;
+        self.st = st;
+        self.hires = hires;
+        self.planes = planes;
+        self.halted = halted;
+        self.rpl = rpl;
+        self.audio_pattern = audio_pattern;
+        self.pitch = pitch;
+        self.rng = rng;
+        self.ram = ram.to_vec();
+        self.screen = screen.to_vec();
+        self.vblank = false;
+        Ok(())
+    }
+}
+
+// The platforms are stored as a single byte
+pub(crate) fn platform_id(platform: Platform) -> u8 {
+    match platform {
+        Platform::Chip8 => 0,
+        Platform::SuperChip => 1,
+        Platform::XoChip => 2,
+    }
+}
+
+pub(crate) fn platform_from_id(id: u8) -> Option<Platform> {
+    match id {
+        0 => Some(Platform::Chip8),
+        1 => Some(Platform::SuperChip),
+        2 => Some(Platform::XoChip),
+        _ => None,
+    }
+}
@@ -0,0 +1,1115 @@
+use crate::asm::assemble;
+use crate::*;
+
+// Load the program on the CPU, and run it until the PC goes past its last instruction
+fn run_on(mut cpu: CPU, source: &str) -> CPU {
+    let rom = assemble(source, cpu.platform()).unwrap();
+    let end = START_ADDR + rom.len() as u16;
+    cpu.load(&rom).unwrap();
+    for _ in 0..1000 {
+        if cpu.pc >= end || cpu.is_halted() {
+            return cpu;
+        }
+        cpu.tick().unwrap();
+    }
+    panic!("the program did not end");
+}
+
+fn run(source: &str) -> CPU {
+    run_on(CPU::with_seed(0), source)
+}
+
+fn run_with_quirks(quirks: Quirks, source: &str) -> CPU {
+    let mut cpu = CPU::with_quirks(quirks);
+    cpu.set_seed(0);
+    run_on(cpu, source)
+}
+
+fn run_on_platform(platform: Platform, source: &str) -> CPU {
+    let mut cpu = CPU::with_platform(platform);
+    cpu.set_seed(0);
+    run_on(cpu, source)
+}
+
+// Load the program, and run the given number of instructions
+fn step(source: &str, ticks: usize) -> CPU {
+    let mut cpu = CPU::with_seed(0);
+    cpu.load(&assemble(source, Platform::Chip8).unwrap())
+        .unwrap();
+    for _ in 0..ticks {
+        cpu.tick().unwrap();
+    }
+    cpu
+}
+
+fn step_on(platform: Platform, source: &str, ticks: usize) -> CPU {
+    let mut cpu = CPU::with_platform(platform);
+    cpu.load(&assemble(source, platform).unwrap()).unwrap();
+    for _ in 0..ticks {
+        cpu.tick().unwrap();
+    }
+    cpu
+}
+
+fn lit_pixels(cpu: &CPU) -> usize {
+    cpu.screen.iter().filter(|&&pixel| pixel != 0).count()
+}
+
+#[test]
+fn decode_encode_round_trip() {
+    for op in 0..=0xFFFF {
+        if let Some(instruction) = Instruction::decode(op) {
+            assert_eq!(instruction.encode(), op, "{}", instruction);
+        }
+    }
+}
+
+#[test]
+fn op_00e0_clears_the_screen() {
+    assert_eq!(Instruction::decode(0x00E0), Some(Instruction::Cls));
+    let cpu = run("LD I, 0\n DRW V0, V0, 5");
+    assert_eq!(lit_pixels(&cpu), 14);
+    let cpu = run("LD I, 0\n DRW V0, V0, 5\n CLS");
+    assert_eq!(lit_pixels(&cpu), 0);
+}
+
+#[test]
+fn op_2nnn_00ee_call_and_return() {
+    let cpu = step("CALL sub\n sub: RET", 1);
+    assert_eq!(cpu.pc, 0x202);
+    assert_eq!(cpu.stack(), &[0x202]);
+    let cpu = step("CALL sub\n sub: RET", 2);
+    assert_eq!(cpu.pc, 0x202);
+    assert_eq!(cpu.sp, 0);
+}
+
+#[test]
+fn op_00ee_on_an_empty_stack_fails() {
+    let mut cpu = CPU::new();
+    cpu.load(&assemble("RET", Platform::Chip8).unwrap())
+        .unwrap();
+    assert_eq!(cpu.tick(), Err(Chip8Error::StackUnderflow));
+}
+
+#[test]
+fn op_2nnn_past_the_stack_size_fails() {
+    let mut cpu = CPU::new();
+    cpu.load(&assemble("start: CALL start", Platform::Chip8).unwrap())
+        .unwrap();
+    for _ in 0..STACK_SIZE {
+        cpu.tick().unwrap();
+    }
+    assert_eq!(cpu.tick(), Err(Chip8Error::StackOverflow));
+}
+
+#[test]
+fn op_1nnn_jumps() {
+    let cpu = step("JP 0x300", 1);
+    assert_eq!(cpu.pc, 0x300);
+}
+
+#[test]
+fn op_3xnn_4xnn_skip_on_byte() {
+    let cpu = run("LD V0, 5\n SE V0, 5\n LD V1, 1\n SE V0, 6\n LD V2, 1");
+    assert_eq!((cpu.v_reg[1], cpu.v_reg[2]), (0, 1));
+    let cpu = run("LD V0, 5\n SNE V0, 5\n LD V1, 1\n SNE V0, 6\n LD V2, 1");
+    assert_eq!((cpu.v_reg[1], cpu.v_reg[2]), (1, 0));
+}
+
+#[test]
+fn op_5xy0_9xy0_skip_on_register() {
+    let cpu = run("LD V0, 5\n LD V1, 5\n SE V0, V1\n LD V2, 1\n SNE V0, V1\n LD V3, 1");
+    assert_eq!((cpu.v_reg[2], cpu.v_reg[3]), (0, 1));
+}
+
+#[test]
+fn op_6xnn_7xnn_load_and_add_byte() {
+    let cpu = run("LD V0, 0xFE\n ADD V0, 3\n LD VF, 7");
+    assert_eq!(cpu.v_reg[0], 1);
+    // 7XNN never touches the carry flag
+    assert_eq!(cpu.v_reg[0xF], 7);
+}
+
+#[test]
+fn op_8xy0_loads_register() {
+    let cpu = run("LD V1, 42\n LD V0, V1");
+    assert_eq!(cpu.v_reg[0], 42);
+}
+
+#[test]
+fn op_8xy1_8xy2_8xy3_bitwise() {
+    let source = |op| format!("LD V0, 0b1100\n LD V1, 0b1010\n {} V0, V1", op);
+    assert_eq!(run(&source("OR")).v_reg[0], 0b1110);
+    assert_eq!(run(&source("AND")).v_reg[0], 0b1000);
+    assert_eq!(run(&source("XOR")).v_reg[0], 0b0110);
+}
+
+#[test]
+fn op_8xy4_adds_with_carry() {
+    let cpu = run("LD V0, 200\n LD V1, 100\n ADD V0, V1");
+    assert_eq!((cpu.v_reg[0], cpu.v_reg[0xF]), (44, 1));
+    let cpu = run("LD V0, 20\n LD V1, 10\n ADD V0, V1");
+    assert_eq!((cpu.v_reg[0], cpu.v_reg[0xF]), (30, 0));
+}
+
+#[test]
+fn op_8xy5_subtracts_with_borrow() {
+    let cpu = run("LD V0, 30\n LD V1, 10\n SUB V0, V1");
+    assert_eq!((cpu.v_reg[0], cpu.v_reg[0xF]), (20, 1));
+    let cpu = run("LD V0, 10\n LD V1, 30\n SUB V0, V1");
+    assert_eq!((cpu.v_reg[0], cpu.v_reg[0xF]), (236, 0));
+}
+
+#[test]
+fn op_8xy7_subtracts_reversed_with_borrow() {
+    let cpu = run("LD V0, 10\n LD V1, 30\n SUBN V0, V1");
+    assert_eq!((cpu.v_reg[0], cpu.v_reg[0xF]), (20, 1));
+    let cpu = run("LD V0, 30\n LD V1, 10\n SUBN V0, V1");
+    assert_eq!((cpu.v_reg[0], cpu.v_reg[0xF]), (236, 0));
+}
+
+#[test]
+fn op_8xy6_8xye_shift() {
+    let cpu = run("LD V0, 0b10000011\n SHR V0");
+    assert_eq!((cpu.v_reg[0], cpu.v_reg[0xF]), (0b01000001, 1));
+    let cpu = run("LD V0, 0b10000010\n SHL V0");
+    assert_eq!((cpu.v_reg[0], cpu.v_reg[0xF]), (0b00000100, 1));
+}
+
+#[test]
+fn op_8xy6_without_the_shift_quirk_shifts_vy() {
+    let quirks = Quirks::COSMAC_VIP;
+    let cpu = run_with_quirks(quirks, "LD V0, 0\n LD V1, 0b100\n SHR V0, V1");
+    assert_eq!((cpu.v_reg[0], cpu.v_reg[1]), (0b10, 0b100));
+}
+
+#[test]
+fn op_8xy1_with_the_vf_reset_quirk_clears_vf() {
+    let source = "LD VF, 5\n LD V0, 1\n OR V0, V0";
+    assert_eq!(run(source).v_reg[0xF], 5);
+    assert_eq!(run_with_quirks(Quirks::COSMAC_VIP, source).v_reg[0xF], 0);
+}
+
+#[test]
+fn op_annn_loads_i() {
+    let cpu = run("LD I, 0x123");
+    assert_eq!(cpu.i_reg, 0x123);
+}
+
+#[test]
+fn op_bnnn_jumps_with_offset() {
+    let cpu = step("LD V0, 4\n LD V3, 8\n JP V0, 0x300", 3);
+    assert_eq!(cpu.pc, 0x304);
+    let mut cpu = CPU::with_quirks(Quirks::SUPER_CHIP);
+    cpu.load(&assemble("LD V0, 4\n LD V3, 8\n JP V0, 0x300", Platform::Chip8).unwrap())
+        .unwrap();
+    for _ in 0..3 {
+        cpu.tick().unwrap();
+    }
+    assert_eq!(cpu.pc, 0x308);
+}
+
+#[test]
+fn op_cxnn_is_masked_and_seeded() {
+    let cpu = run("RND V0, 0x0F\n RND V1, 0xFF");
+    assert_eq!(cpu.v_reg[0] & 0xF0, 0);
+    let again = run("RND V0, 0x0F\n RND V1, 0xFF");
+    assert_eq!(cpu.v_reg, again.v_reg);
+}
+
+#[test]
+fn op_dxyn_draws_and_detects_collisions() {
+    let cpu = run("LD V0, 10\n LD V1, 5\n LD I, 0\n DRW V0, V1, 5");
+    // The 0 of the font is a 4x5 square outline
+    assert_eq!(
+        cpu.screen[5 * SCREEN_WIDTH + 10..5 * SCREEN_WIDTH + 14],
+        [1, 1, 1, 1]
+    );
+    assert_eq!(
+        cpu.screen[6 * SCREEN_WIDTH + 10..6 * SCREEN_WIDTH + 14],
+        [1, 0, 0, 1]
+    );
+    assert_eq!(cpu.v_reg[0xF], 0);
+    let cpu = run("LD I, 0\n DRW V0, V1, 5\n DRW V0, V1, 5");
+    assert_eq!((lit_pixels(&cpu), cpu.v_reg[0xF]), (0, 1));
+}
+
+#[test]
+fn op_dxyn_wraps_or_clips_at_the_edges() {
+    let source = "LD V0, 62\n LD I, 0\n DRW V0, V1, 1";
+    assert_eq!(lit_pixels(&run(source)), 4);
+    assert_eq!(lit_pixels(&run_with_quirks(Quirks::CHIP_48, source)), 2);
+}
+
+#[test]
+fn op_dxyn_on_super_chip_waits_in_lo_res_and_counts_rows_in_hi_res() {
+    // The sprite waits for the next frame in lo-res only
+    let cpu = step_on(Platform::SuperChip, "LD I, 0\n DRW V0, V1, 5", 3);
+    assert_eq!((cpu.pc, lit_pixels(&cpu)), (0x202, 0));
+    let cpu = step_on(Platform::SuperChip, "HIGH\n LD I, 0\n DRW V0, V1, 5", 3);
+    assert_eq!((cpu.pc, lit_pixels(&cpu)), (0x206, 14));
+    // V[F] holds the number of rows which collided, along with the ones clipped at the bottom
+    let source = "HIGH\n LD I, 0\n DRW V0, V1, 5\n LD V1, 2\n DRW V0, V1, 5";
+    assert_eq!(step_on(Platform::SuperChip, source, 5).v_reg[0xF], 3);
+    let source = "HIGH\n LD V1, 62\n LD I, 0\n DRW V0, V1, 5";
+    assert_eq!(step_on(Platform::SuperChip, source, 4).v_reg[0xF], 3);
+    // Without the row count on CHIP-48
+    let mut cpu = CPU::with_quirks(Quirks::CHIP_48);
+    cpu.load(&assemble("LD V1, 30\n LD I, 0\n DRW V0, V1, 5", Platform::Chip8).unwrap())
+        .unwrap();
+    for _ in 0..3 {
+        cpu.tick().unwrap();
+    }
+    assert_eq!(cpu.v_reg[0xF], 0);
+}
+
+#[test]
+fn op_ex9e_exa1_skip_on_keys() {
+    let source = "LD V0, 5\n SKP V0\n LD V1, 1\n SKNP V0\n LD V2, 1";
+    let mut cpu = CPU::with_seed(0);
+    cpu.keypress(5, true);
+    let cpu = run_on(cpu, source);
+    assert_eq!((cpu.v_reg[1], cpu.v_reg[2]), (0, 1));
+    let cpu = run(source);
+    assert_eq!((cpu.v_reg[1], cpu.v_reg[2]), (1, 0));
+}
+
+#[test]
+fn op_fx07_fx15_fx18_timers() {
+    let mut cpu = step("LD V0, 10\n LD DT, V0\n LD ST, V0", 3);
+    cpu.tick_timers();
+    assert_eq!((cpu.dt, cpu.st), (9, 9));
+    let cpu = run("LD V0, 10\n LD DT, V0\n LD V1, DT");
+    assert_eq!(cpu.v_reg[1], 10);
+}
+
+#[test]
+fn the_buzzer_sounds_while_the_sound_timer_is_active() {
+    let mut cpu = step("LD V0, 2\n LD ST, V0", 1);
+    assert!(!cpu.is_sound_active());
+    cpu.tick().unwrap();
+    assert!(cpu.is_sound_active());
+    cpu.tick_timers();
+    assert!(cpu.is_sound_active());
+    cpu.tick_timers();
+    assert!(!cpu.is_sound_active());
+}
+
+#[test]
+fn audio_renders_a_square_wave_while_the_buzzer_sounds() {
+    let mut cpu = step("LD V0, 2\n LD ST, V0", 2);
+    let mut audio = Audio::new(44100);
+    audio.set_volume(0.5);
+    audio.set_tone(441.0);
+
+    // A period of 100 samples, half of it high
+    let mut samples = [1.0; 150];
+    audio.update(&cpu);
+    audio.fill(&mut samples);
+    assert!(samples[..50].iter().all(|&sample| sample == 0.5));
+    assert!(samples[50..100].iter().all(|&sample| sample == -0.5));
+    assert_eq!(samples[100], 0.5);
+
+    cpu.tick_timers();
+    cpu.tick_timers();
+    audio.update(&cpu);
+    audio.fill(&mut samples);
+    assert!(samples.iter().all(|&sample| sample == 0.0));
+}
+
+#[test]
+fn audio_plays_the_xo_chip_pattern_at_its_pitch() {
+    let mut cpu = step_on(
+        Platform::XoChip,
+        "LD I, pattern\n AUDIO\n LD V0, 112\n PITCH V0\n LD V0, 2\n LD ST, V0\n          pattern: db 0xF0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0",
+        6,
+    );
+    // A pitch of 112 is an octave above 64, so 8000 bits per second, or one sample per bit at 8000 Hz
+    let mut audio = Audio::new(8000);
+    audio.set_volume(1.0);
+    audio.update(&cpu);
+    let mut samples = [0.0; 136];
+    audio.fill(&mut samples);
+    assert_eq!(samples[..4], [1.0; 4]);
+    assert!(samples[4..128].iter().all(|&sample| sample == -1.0));
+    assert_eq!(samples[128..132], [1.0; 4]);
+
+    cpu.set_st(0);
+    audio.update(&cpu);
+    audio.fill(&mut samples);
+    assert_eq!(samples[0], 0.0);
+}
+
+#[test]
+fn op_fx0a_waits_for_a_key() {
+    let mut cpu = step("LD V0, K", 3);
+    assert_eq!(cpu.pc, 0x200);
+    cpu.keypress(7, true);
+    cpu.tick().unwrap();
+    assert_eq!((cpu.pc, cpu.v_reg[0]), (0x202, 7));
+}
+
+#[test]
+fn op_fx1e_adds_to_i() {
+    let cpu = run("LD I, 0x100\n LD V0, 0x20\n ADD I, V0");
+    assert_eq!(cpu.i_reg, 0x120);
+}
+
+#[test]
+fn op_fx29_points_to_the_font() {
+    let cpu = run("LD V0, 0xA\n LD F, V0");
+    assert_eq!(cpu.i_reg, 0xA * 5);
+}
+
+#[test]
+fn op_fx33_stores_bcd() {
+    let cpu = run("LD V0, 254\n LD I, 0x300\n LD B, V0");
+    assert_eq!(cpu.ram[0x300..0x303], [2, 5, 4]);
+}
+
+#[test]
+fn op_fx55_fx65_store_and_load_registers() {
+    let cpu = run("LD V0, 1\n LD V1, 2\n LD V2, 3\n LD I, 0x300\n LD [I], V2");
+    assert_eq!(cpu.ram[0x300..0x304], [1, 2, 3, 0]);
+    assert_eq!(cpu.i_reg, 0x300);
+    let cpu = run("LD I, 0\n LD V1, [I]");
+    assert_eq!(cpu.v_reg[..3], [0xF0, 0x90, 0]);
+}
+
+#[test]
+fn op_fx55_with_the_memory_quirk_increments_i() {
+    let cpu = run_with_quirks(Quirks::COSMAC_VIP, "LD I, 0x300\n LD [I], V2");
+    assert_eq!(cpu.i_reg, 0x303);
+}
+
+#[test]
+fn unknown_opcodes_fail() {
+    let mut cpu = CPU::new();
+    cpu.load(&[0xFF, 0xFF]).unwrap();
+    assert_eq!(
+        cpu.tick(),
+        Err(Chip8Error::UnknownOpcode {
+            pc: 0x200,
+            op: 0xFFFF
+        })
+    );
+    // The SUPER-CHIP instructions are unknown to CHIP-8
+    let mut cpu = CPU::new();
+    cpu.load(&assemble("HIGH", Platform::SuperChip).unwrap())
+        .unwrap();
+    assert!(cpu.tick().is_err());
+}
+
+#[test]
+fn roms_must_fit_in_the_ram() {
+    let mut cpu = CPU::new();
+    assert_eq!(
+        cpu.load(&[0; 4097 - 0x200]),
+        Err(Chip8Error::RomTooLarge {
+            size: 3585,
+            max: 3584
+        })
+    );
+    assert_eq!(cpu.rom_hash(), [0; 20]);
+    assert!(cpu.load(&[0; 4096 - 0x200]).is_ok());
+    // XO-CHIP has 64 KB of RAM
+    let mut cpu = CPU::with_platform(Platform::XoChip);
+    assert!(cpu.load(&[0; 4097 - 0x200]).is_ok());
+}
+
+#[test]
+fn keys_past_f_are_ignored() {
+    let mut cpu = CPU::new();
+    cpu.keypress(0xF, true);
+    cpu.keypress(16, true);
+    cpu.keypress(usize::MAX, true);
+    assert_eq!(cpu.keys().iter().filter(|&&key| key).count(), 1);
+}
+
+#[test]
+fn super_chip_resolution_and_exit() {
+    let cpu = run_on_platform(Platform::SuperChip, "HIGH");
+    assert_eq!(cpu.get_display().width, HIRES_SCREEN_WIDTH);
+    let cpu = run_on_platform(Platform::SuperChip, "HIGH\n LOW");
+    assert_eq!(cpu.get_display().width, SCREEN_WIDTH);
+    let cpu = run_on_platform(Platform::SuperChip, "EXIT\n LD V0, 1");
+    assert!(cpu.is_halted());
+    assert_eq!(cpu.v_reg[0], 0);
+}
+
+#[test]
+fn super_chip_rpl_flags() {
+    let source = "LD V0, 1\n LD V1, 2\n LD R, V1\n LD V0, 0\n LD V1, 0\n LD V1, R";
+    let cpu = run_on_platform(Platform::SuperChip, source);
+    assert_eq!(cpu.v_reg[..2], [1, 2]);
+}
+
+#[test]
+fn xo_chip_long_load_and_register_ranges() {
+    let cpu = run_on_platform(Platform::XoChip, "LD I, LONG 0x1234");
+    assert_eq!(cpu.i_reg, 0x1234);
+    let source = "LD V1, 1\n LD V2, 2\n LD I, 0x300\n SAVE V2, V1\n LOAD V3, V4";
+    let cpu = run_on_platform(Platform::XoChip, source);
+    assert_eq!(cpu.ram[0x300..0x302], [2, 1]);
+    assert_eq!(cpu.v_reg[3..5], [2, 1]);
+}
+
+#[test]
+fn save_states_round_trip() {
+    let mut cpu = step(
+        "LD V0, 5\n LD I, 0\n DRW V0, V0, 5\n start: ADD V0, 1\n JP start",
+        10,
+    );
+    let state = cpu.save_state();
+    let v_reg = cpu.v_reg;
+    cpu.tick().unwrap();
+    cpu.load_state(&state).unwrap();
+    assert_eq!(cpu.v_reg, v_reg);
+    assert_eq!(cpu.save_state(), state);
+    assert_eq!(cpu.load_state(&state[..10]), Err(StateError::Truncated));
+    assert_eq!(CPU::new().load_state(&state), Err(StateError::RomMismatch));
+}
+
+#[test]
+fn save_states_resume_a_game_where_it_was_left() {
+    let rom = include_bytes!("../../roms/PONG");
+    // Play a few seconds of the game, moving the left paddle up and down
+    let play = |cpu: &mut CPU, frames: std::ops::Range<u32>| {
+        for frame in frames {
+            cpu.keypress(1, frame % 74 < 37);
+            for _ in 0..10 {
+                cpu.tick().unwrap();
+            }
+            cpu.tick_timers();
+        }
+    };
+    let mut cpu = CPU::with_seed(42);
+    cpu.load(rom).unwrap();
+    play(&mut cpu, 0..300);
+    let state = cpu.save_state();
+
+    // The state is restored on another CPU running the same ROM, with a different seed which it replaces
+    let mut restored = CPU::with_seed(7);
+    restored.load(rom).unwrap();
+    restored.load_state(&state).unwrap();
+    assert_eq!(restored.save_state(), state);
+    play(&mut cpu, 300..600);
+    play(&mut restored, 300..600);
+    assert_eq!(restored.save_state(), cpu.save_state());
+    assert_eq!(restored.screen, cpu.screen);
+}
+
+#[test]
+fn introspection_and_mutators() {
+    let mut cpu = step("LD V3, 7\n LD I, 0x300\n CALL sub\n sub: LD DT, V3", 4);
+    let state = cpu.cpu_state();
+    assert_eq!((state.pc, state.i_reg, state.v_reg[3]), (0x208, 0x300, 7));
+    assert_eq!((state.stack, state.dt), (vec![0x206], 7));
+    assert_eq!((state.hires, state.planes, state.halted), (false, 1, false));
+    let state = step_on(Platform::XoChip, "HIGH\n PLANE 3", 2).cpu_state();
+    assert_eq!((state.hires, state.planes), (true, 3));
+    assert_eq!(cpu.peek(0x200), Some(0x63));
+    assert_eq!(cpu.peek(RAM_SIZE), None);
+
+    cpu.set_v_reg(0x13, 9);
+    cpu.set_pc(0x1202);
+    assert_eq!((cpu.v_reg()[3], cpu.pc()), (9, 0x202));
+    cpu.write_memory(0x300, 0xAB).unwrap();
+    assert_eq!(cpu.memory()[0x300], 0xAB);
+    assert_eq!(
+        cpu.write_memory(RAM_SIZE, 0),
+        Err(Chip8Error::MemoryOutOfBounds { addr: RAM_SIZE })
+    );
+}
+
+#[test]
+fn watchpoints_halt_on_writes() {
+    let mut cpu = CPU::with_seed(0);
+    cpu.load(
+        &assemble(
+            "LD V0, 123\n LD I, 0x300\n LD B, V0\n LD V1, 1",
+            Platform::Chip8,
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    let id = cpu.add_watchpoint(WatchTarget::Write(0x301..0x302), WatchAction::Halt);
+    for _ in 0..4 {
+        cpu.tick().unwrap();
+    }
+    // The program halts once FX33 is done, until the hit is taken
+    let hit = cpu.take_watch_hit().unwrap();
+    assert_eq!(
+        (hit.id, hit.pc, hit.kind),
+        (id, 0x204, HitKind::Write { addr: 0x301 })
+    );
+    assert_eq!((hit.old, hit.new, cpu.pc()), (0, 2, 0x206));
+    cpu.tick().unwrap();
+    assert_eq!((cpu.v_reg()[1], cpu.take_watch_hit()), (1, None));
+}
+
+#[test]
+fn watchpoints_call_back_on_reads_and_registers() {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    let hits = Rc::new(RefCell::new(Vec::new()));
+    let mut cpu = CPU::with_seed(0);
+    cpu.load(
+        &assemble(
+            "LD I, 0x300\n LD V1, [I]\n ADD V1, 2\n ADD I, V1",
+            Platform::Chip8,
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    cpu.write_memory(0x301, 5).unwrap();
+    for target in [
+        WatchTarget::Read(0x301..0x302),
+        WatchTarget::V(1),
+        WatchTarget::I,
+    ] {
+        let hits = hits.clone();
+        let callback = move |hit: &WatchHit| hits.borrow_mut().push((hit.pc, hit.kind, hit.new));
+        cpu.add_watchpoint(target, WatchAction::Callback(Box::new(callback)));
+    }
+    for _ in 0..4 {
+        cpu.tick().unwrap();
+    }
+    assert_eq!(
+        *hits.borrow(),
+        [
+            (0x200, HitKind::I, 0x300),
+            (0x202, HitKind::Read { addr: 0x301 }, 5),
+            (0x202, HitKind::V(1), 5),
+            (0x204, HitKind::V(1), 7),
+            (0x206, HitKind::I, 0x307),
+        ]
+    );
+    assert_eq!(cpu.take_watch_hit(), None);
+}
+
+#[test]
+fn traces_keep_the_latest_lines_in_range() {
+    let mut cpu = CPU::with_seed(0);
+    cpu.load(
+        &assemble(
+            "LD V0, 1\n loop: ADD V0, 1\n SE V0, 4\n JP loop\n LD I, 0x123",
+            Platform::Chip8,
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    cpu.set_trace(Trace::ring(3).with_filter(0x202..0x208));
+    for _ in 0..9 {
+        cpu.tick().unwrap();
+    }
+    let trace = cpu.take_trace().unwrap();
+    let lines: Vec<_> = trace.lines().collect();
+    assert_eq!(lines.len(), 3);
+    assert!(lines[0].starts_with("0x206 1202     JP 0x202"));
+    assert!(lines[1].starts_with("0x202 7001     ADD V0, 0x01"));
+    assert!(lines[2].starts_with("0x204 3004     SE V0, 0x04"));
+    assert!(lines[2].contains(" V0=04 V1=00 ") && lines[2].ends_with(" I=0000 SP=0 DT=00 ST=00"));
+
+    // The program goes on without being traced
+    cpu.tick().unwrap();
+    assert_eq!(cpu.i_reg(), 0x123);
+}
+
+#[test]
+fn trace_diffs_find_the_first_divergence() {
+    let a =
+        "# A comment\n0x200 6001 LD V0, 0x01 V0=00 I=0000\n0x202 7001 ADD V0, 0x01 V0=01 I=0000\n";
+    let b = "0x200 6001 ld v0, 1 V0=00\n\n0x202 7001 add v0, 1 V0=02\n0x204 1200 jp 0x200 V0=03\n";
+    let divergence = tracediff::diff_traces(a, b, 1).unwrap();
+    assert_eq!(divergence.index, 1);
+    assert_eq!(divergence.differences, ["V0 0x01 != 0x02"]);
+    assert_eq!(divergence.before, ["0x200 6001 LD V0, 0x01 V0=00 I=0000"]);
+    assert_eq!((divergence.a.len(), divergence.b.len()), (1, 2));
+
+    let divergence = tracediff::diff_traces(a, &b.replace("V0=02", "V0=01"), 1).unwrap();
+    assert_eq!((divergence.index, divergence.a.len()), (2, 0));
+    assert_eq!(divergence.differences, ["A ends first"]);
+    assert_eq!(tracediff::diff_traces(a, a, 5), None);
+}
+
+#[test]
+fn cpu_diffs_compare_quirks() {
+    let rom = assemble(
+        "LD V0, 1\n LD V1, 4\n SHR V0, V1\n LD I, 0x300\n LD [I], V1",
+        Platform::Chip8,
+    )
+    .unwrap();
+    let [mut a, mut b] = [Quirks::MODERN, Quirks::COSMAC_VIP].map(|quirks| {
+        let mut cpu = CPU::with_quirks(quirks);
+        cpu.load(&rom).unwrap();
+        cpu
+    });
+    let divergence = tracediff::diff_cpus(&mut a, &mut b, 100, 10, 1).unwrap();
+    assert_eq!(divergence.index, 3);
+    assert_eq!(
+        divergence.differences,
+        ["V0 0x00 != 0x02", "VF 0x01 != 0x00"]
+    );
+    assert_eq!(divergence.before.len(), 1);
+    assert!(divergence.a[0].starts_with("0x206 A300"));
+
+    // FX55 leaves I pointing after V0 on the COSMAC VIP
+    let rom = assemble(
+        "LD V0, 5\n LD I, 0x300\n LD [I], V0\n LD I, 0x300",
+        Platform::Chip8,
+    )
+    .unwrap();
+    let [mut a, mut b] = [Quirks::MODERN, Quirks::COSMAC_VIP].map(|quirks| {
+        let mut cpu = CPU::with_quirks(quirks);
+        cpu.load(&rom).unwrap();
+        cpu
+    });
+    let divergence = tracediff::diff_cpus(&mut a, &mut b, 100, 10, 1).unwrap();
+    assert_eq!(divergence.index, 3);
+    assert_eq!(divergence.differences, ["I 0x300 != 0x301"]);
+}
+
+// Run the program after `Action::Resume` the way the frontends do, until the debugger stops it
+fn resume(cpu: &mut CPU, debugger: &mut debugger::Debugger) -> String {
+    for _ in 0..1000 {
+        if let Some(reason) = debugger.check(cpu) {
+            return reason;
+        }
+        cpu.tick().unwrap();
+    }
+    panic!("the debugger did not stop the program");
+}
+
+#[test]
+fn debugger_breakpoints_and_watches() {
+    use debugger::{Action, Debugger};
+    let mut cpu = CPU::with_seed(0);
+    cpu.load(
+        &assemble(
+            "LD V0, 1\n LD V1, 2\n stop: LD V2, 3\n JP stop",
+            Platform::Chip8,
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    let mut debugger = Debugger::new();
+
+    debugger.command(&mut cpu, "break 0x204");
+    assert_eq!(debugger.command(&mut cpu, "continue").0, Action::Resume);
+    assert!(resume(&mut cpu, &mut debugger).starts_with("Breakpoint at 0x204"));
+    assert_eq!(cpu.pc(), 0x204);
+
+    debugger.command(&mut cpu, "delete 0x204");
+    // The watched register changes on the very first instruction, so the program doesn't even resume
+    debugger.command(&mut cpu, "watch V2");
+    let (action, output) = debugger.command(&mut cpu, "c");
+    assert_eq!(action, Action::Prompt);
+    assert!(output.starts_with("Watch V2: 0x00 -> 0x03"));
+    assert_eq!(debugger.command(&mut cpu, "quit").0, Action::Quit);
+}
+
+#[test]
+fn debugger_steps_over_subroutines() {
+    use debugger::{Action, Debugger};
+    let mut cpu = CPU::with_seed(0);
+    cpu.load(&assemble("CALL sub\n LD V1, 1\n sub: LD V0, 1\n RET", Platform::Chip8).unwrap())
+        .unwrap();
+    let mut debugger = Debugger::new();
+
+    assert_eq!(debugger.command(&mut cpu, "next").0, Action::Resume);
+    resume(&mut cpu, &mut debugger);
+    assert_eq!((cpu.pc(), cpu.v_reg()[0]), (0x202, 1));
+    let (action, _) = debugger.command(&mut cpu, "");
+    assert_eq!(
+        (action, cpu.pc(), cpu.v_reg()[1]),
+        (Action::Prompt, 0x204, 1)
+    );
+
+    debugger.command(&mut cpu, "set i 0x300+2");
+    debugger.command(&mut cpu, "set pc 0x200");
+    assert_eq!((cpu.i_reg(), cpu.pc()), (0x302, 0x200));
+    debugger.command(&mut cpu, "step 2");
+    assert_eq!((cpu.pc(), cpu.stack()), (0x206, &[0x202][..]));
+}
+
+#[test]
+fn configs_merge_the_rom_settings_over_the_defaults() {
+    use config::{Config, Settings};
+    let rom = assemble("CLS", Platform::Chip8).unwrap();
+    let mut cpu = CPU::new();
+    cpu.load(&rom).unwrap();
+    let text = format!(
+        "[defaults]\nips = 700\nvolume = 50\n\n[roms.{}]\nquirks = \"vip\"\nips = 1000\n",
+        config::rom_key(&cpu.rom_hash()).to_uppercase()
+    );
+    let mut config = Config::parse(&text).unwrap();
+
+    let settings = config.settings(&cpu.rom_hash());
+    assert_eq!((settings.ips, settings.volume), (Some(1000), Some(50)));
+    assert_eq!(settings.quirks(), Some(Quirks::COSMAC_VIP));
+    assert_eq!(config.settings(&[0; 20]).ips, Some(700));
+
+    // The changes are kept along with the other settings of the ROM, and written back
+    let changes = Settings {
+        platform: Some("schip".to_string()),
+        ips: Some(1200),
+        ..Settings::default()
+    };
+    config.update(&cpu.rom_hash(), &changes);
+    let config = Config::parse(&config.to_toml()).unwrap();
+    let settings = config.settings(&cpu.rom_hash());
+    assert_eq!(settings.platform(), Some(Platform::SuperChip));
+    assert_eq!(
+        (settings.ips, settings.quirks.as_deref()),
+        (Some(1200), Some("vip"))
+    );
+}
+
+#[test]
+fn configs_reject_invalid_settings() {
+    use config::Config;
+    assert!(Config::parse("").unwrap().roms.is_empty());
+    assert!(Config::parse("[defaults]\nplatform = \"nes\"").is_err());
+    assert!(Config::parse("[defaults]\nforeground = \"FFF\"").is_err());
+    assert!(Config::parse("[defaults]\nspeed = 3").is_err());
+    assert!(Config::parse("[roms.PONG]\nips = 600").is_err());
+}
+
+#[test]
+fn the_database_knows_the_bundled_roms() {
+    let invaders = config::hash_rom(include_bytes!("../../roms/INVADERS"));
+    let info = database::lookup(&invaders).unwrap();
+    assert_eq!(info.title, "Space Invaders");
+    assert_eq!(info.controls(), "left 4, right 6, a 5");
+
+    // The settings of the database apply over the defaults, unless the config file overrides them for the ROM
+    // The bundled ROMs run with the CHIP-48 quirks at 10 instructions a frame, as in the golden tests
+    let mut config = config::Config::parse("[defaults]\nips = 700\nquirks = \"vip\"").unwrap();
+    let unknown = config.settings(&[0; 20]);
+    assert_eq!(
+        (unknown.quirks(), unknown.ips),
+        (Some(Quirks::COSMAC_VIP), Some(700))
+    );
+    let settings = config.settings(&invaders);
+    assert_eq!(settings.platform(), Some(Platform::Chip8));
+    assert_eq!(settings.quirks(), Some(Quirks::CHIP_48));
+    assert_eq!(settings.ips, Some(600));
+    let changes = config::Settings {
+        quirks: Some("modern".to_string()),
+        ips: Some(900),
+        ..config::Settings::default()
+    };
+    config.update(&invaders, &changes);
+    let settings = config.settings(&invaders);
+    assert_eq!(
+        (settings.quirks(), settings.ips),
+        (Some(Quirks::MODERN), Some(900))
+    );
+
+    let pong = config::hash_rom(include_bytes!("../../roms/PONG"));
+    assert_eq!(
+        database::lookup(&pong).unwrap().controls(),
+        "up 1, down 4, player2Down D, player2Up C"
+    );
+    assert_eq!(database::lookup(&[0; 20]), None);
+}
+
+#[test]
+fn keymaps_bind_presets_and_overrides() {
+    let keypad = Keymap::default();
+    assert_eq!(
+        (keypad.key("KeyQ"), keypad.key("KeyX")),
+        (Some(0x4), Some(0x0))
+    );
+    assert_eq!(keypad.key("Numpad4"), None);
+    let numpad: Keymap = "numpad".parse().unwrap();
+    assert_eq!(numpad.key("NumpadDecimal"), Some(0xF));
+    assert!("azerty".parse::<Keymap>().is_err());
+
+    // The keys of the defaults and of the ROM are bound on top of the preset
+    let text = format!(
+        "[defaults.keys]\nSpace = 5\n\n[roms.{}]\nkeymap = \"numpad\"\nkeys = {{ ArrowLeft = 4, Space = 0xA }}\n",
+        config::rom_key(&[0; 20])
+    );
+    let keymap = config::Config::parse(&text)
+        .unwrap()
+        .settings(&[0; 20])
+        .keymap();
+    assert_eq!(keymap.key("Numpad5"), Some(0x5));
+    assert_eq!(
+        (keymap.key("ArrowLeft"), keymap.key("Space")),
+        (Some(0x4), Some(0xA))
+    );
+    assert_eq!(keymap.key("KeyQ"), None);
+    assert!(config::Config::parse("[defaults.keys]\nSpace = 16").is_err());
+}
+
+#[test]
+fn disassemblies_label_the_jumps_and_sprites() {
+    use disasm::Disassembly;
+    let rom = assemble(
+        "LD I, sprite\n DRW V0, V1, 2\n loop: JP loop\n sprite: DB 0xF0, 0x90\n DB 0x12",
+        Platform::Chip8,
+    )
+    .unwrap();
+    let disassembly = Disassembly::new(&rom, Platform::Chip8);
+    assert_eq!(disassembly.label(0x204), Some("L204"));
+    assert_eq!(disassembly.label(0x206), Some("S206"));
+    assert_eq!(disassembly.label(0x208), None);
+    assert_eq!(
+        disassembly.to_string(),
+        "0x200: LD I, S206
+0x202: DRW V0, V1, 2
+L204:
+0x204: JP L204
+S206:
+0x206: db 0b11110000 ; ####....
+0x207: db 0b10010000 ; #..#....
+0x208: db 0x12
+"
+    );
+}
+
+#[test]
+fn disassemblies_find_the_sprites_of_every_plane() {
+    use disasm::{Disassembly, Line};
+    let sprites = |source: &str| {
+        let mut source = source.to_string();
+        source.push_str("\n loop: JP loop\n sprite:");
+        source.push_str(&"\n DB 0xFF".repeat(80));
+        let disassembly = Disassembly::new(
+            &assemble(&source, Platform::XoChip).unwrap(),
+            Platform::XoChip,
+        );
+        let lines = disassembly.lines().iter();
+        lines
+            .filter(|line| matches!(line, Line::Sprite { .. }))
+            .count()
+    };
+    // A 16x16 sprite takes 32 bytes on each of the selected planes
+    assert_eq!(sprites("LD I, sprite\n DRW V0, V1, 0"), 32);
+    assert_eq!(sprites("PLANE 3\n LD I, sprite\n DRW V0, V1, 0"), 64);
+    assert_eq!(sprites("PLANE 3\n LD I, sprite\n DRW V0, V1, 5"), 10);
+    assert_eq!(sprites("PLANE 0\n LD I, sprite\n DRW V0, V1, 5"), 0);
+}
+
+#[test]
+fn assembler_errors_point_at_the_faulty_operand() {
+    let error = |source: &str| assemble(source, Platform::Chip8).unwrap_err().to_string();
+    assert_eq!(error("CLS\n    FOO V0"), "2:5: unknown instruction: FOO");
+    assert_eq!(error("CLS\nLD V0, 0x100"), "2:8: byte out of range: 256");
+    assert_eq!(
+        error("CLS\n\n  JP nowhere"),
+        "3:6: undefined label or constant: nowhere"
+    );
+    assert_eq!(
+        error("start: CLS\nstart: CLS"),
+        "2:1: start is already defined"
+    );
+    assert_eq!(error("ADD V0,"), "1:8: expected an operand");
+    assert_eq!(error("DRW V0, V1"), "1:1: invalid operands for DRW");
+    assert_eq!(
+        error("LD V0, 0x7FFFFFFFFFFFFFFF + 1"),
+        "1:29: number out of range"
+    );
+    assert_eq!(
+        error("BIG = 0x7FFFFFFFFFFFFFFF\nLD V0, -BIG - BIG"),
+        "2:15: number out of range"
+    );
+    // The instructions of the later platforms are only available on them
+    assert_eq!(
+        error("CLS\n  high"),
+        "2:3: high needs schip, the program is for chip8"
+    );
+    assert_eq!(
+        error("LD I, LONG 0x300"),
+        "1:1: LD needs xochip, the program is for chip8"
+    );
+    let error = |source: &str| {
+        assemble(source, Platform::SuperChip)
+            .unwrap_err()
+            .to_string()
+    };
+    assert_eq!(
+        error("SCR\n PLANE 2"),
+        "2:2: PLANE needs xochip, the program is for schip"
+    );
+    assert!(assemble("HIGH\n SCU 4\n AUDIO", Platform::XoChip).is_ok());
+}
+
+#[test]
+fn assembled_programs_must_fit_in_the_ram_of_the_platform() {
+    // 3584 bytes fit after 0x200 in the 4 KB of CHIP-8, and 65024 in the 64 KB of XO-CHIP
+    let source = "CLS\n".repeat(1793);
+    assert_eq!(
+        assemble(&source, Platform::Chip8).unwrap_err().to_string(),
+        "1793:1: program too large"
+    );
+    assert_eq!(assemble(&source, Platform::XoChip).unwrap().len(), 3586);
+    assert_eq!(
+        assemble(&source[4..], Platform::SuperChip).unwrap().len(),
+        3584
+    );
+}
+
+#[test]
+fn rewind_deltas_round_trip() {
+    use rewind::{diff, patch};
+    let long: Vec<u8> = (0..1000).map(|i| (i * 7) as u8).collect();
+    let mut changed = long.clone();
+    changed[3] ^= 0xFF;
+    changed[200..500].fill(0x55);
+    changed[999] = 0;
+    let pairs: [(&[u8], &[u8]); 5] = [
+        (&[], &[]),
+        (&[1, 2, 3], &[1, 2, 3]),
+        (&long, &changed),
+        // The resolution changes the size of the snapshots both ways
+        (&long, &long[..600]),
+        (&changed[..10], &long),
+    ];
+    for (from, to) in pairs {
+        assert_eq!(patch(from, &diff(from, to)), to);
+        assert_eq!(patch(to, &diff(to, from)), from);
+    }
+    // Only the changed bytes are stored
+    assert!(diff(&long, &changed).len() < 320);
+}
+
+// Run one frame of a program counting the frames in V0, and take a snapshot at its end as the frontends do
+fn rewind_frame(cpu: &mut CPU, rewind: &mut Rewind) {
+    cpu.tick().unwrap();
+    cpu.tick().unwrap();
+    cpu.tick_timers();
+    rewind.record(cpu);
+}
+
+#[test]
+fn rewinding_goes_back_one_frame_at_a_time() {
+    let mut cpu = CPU::new();
+    cpu.load(&assemble("start: ADD V0, 1\n JP start", Platform::Chip8).unwrap())
+        .unwrap();
+    let mut rewind = Rewind::new(100, 1);
+    for _ in 0..5 {
+        rewind_frame(&mut cpu, &mut rewind);
+    }
+    assert_eq!((rewind.len(), cpu.v_reg[0]), (5, 5));
+    // The snapshot of the current frame is skipped, so the first step already goes back
+    for frame in (1..5).rev() {
+        assert!(rewind.step_back(&mut cpu).unwrap());
+        assert_eq!(cpu.v_reg[0], frame);
+    }
+    assert!(!rewind.step_back(&mut cpu).unwrap());
+    assert_eq!(cpu.v_reg[0], 1);
+
+    // The game goes on from where it was rewound to
+    rewind_frame(&mut cpu, &mut rewind);
+    rewind_frame(&mut cpu, &mut rewind);
+    assert!(rewind.step_back(&mut cpu).unwrap());
+    assert_eq!(cpu.v_reg[0], 2);
+}
+
+#[test]
+fn rewinding_keeps_the_latest_snapshots_only() {
+    let mut cpu = CPU::new();
+    cpu.load(&assemble("start: ADD V0, 1\n JP start", Platform::Chip8).unwrap())
+        .unwrap();
+    let mut rewind = Rewind::new(3, 2);
+    for _ in 0..10 {
+        rewind_frame(&mut cpu, &mut rewind);
+    }
+    // The snapshots are taken on frames 1, 3, 5, 7 and 9, of which the last 3 are kept
+    assert_eq!(rewind.len(), 3);
+    for frame in [9, 7, 5] {
+        assert!(rewind.step_back(&mut cpu).unwrap());
+        assert_eq!(cpu.v_reg[0], frame);
+    }
+    assert!(!rewind.step_back(&mut cpu).unwrap());
+    rewind.clear();
+    assert!(rewind.is_empty());
+}
+
+// Run the frames of a movie on the CPU, at 10 instructions per frame, and return how many were played
+fn play_movie(movie: &Movie, cpu: &mut CPU) -> u32 {
+    let mut frame = 0;
+    while movie.play_frame(frame, cpu) {
+        for _ in 0..10 {
+            cpu.tick().unwrap();
+        }
+        cpu.tick_timers();
+        frame += 1;
+    }
+    frame
+}
+
+#[test]
+fn movies_replay_the_recorded_session() {
+    let rom = include_bytes!("../../roms/PONG");
+    let mut movie = Movie::new(rom, Platform::Chip8, Quirks::MODERN, 42, 600);
+    let mut cpu = CPU::new();
+    movie.start(&mut cpu, rom).unwrap();
+    // Move the left paddle up and down, pressing and releasing the key every 37 frames
+    for frame in 0..300 {
+        if frame % 37 == 0 {
+            let pressed = frame % 74 == 0;
+            cpu.keypress(1, pressed);
+            movie.record(1, pressed);
+        }
+        for _ in 0..10 {
+            cpu.tick().unwrap();
+        }
+        cpu.tick_timers();
+        movie.end_frame();
+    }
+
+    let parsed = Movie::from_bytes(&movie.to_bytes()).unwrap();
+    assert_eq!(parsed, movie);
+    assert_eq!((parsed.frames(), parsed.events().len()), (300, 9));
+    // The CPU given to `start` is replaced, along with its seed and quirks
+    let mut replay = CPU::with_quirks(Quirks::COSMAC_VIP);
+    replay.set_seed(7);
+    parsed.start(&mut replay, rom).unwrap();
+    assert_eq!(play_movie(&parsed, &mut replay), 300);
+    assert_eq!(replay.save_state(), cpu.save_state());
+}
+
+#[test]
+fn movies_only_play_on_their_rom() {
+    let rom = include_bytes!("../../roms/PONG");
+    let movie = Movie::new(rom, Platform::Chip8, Quirks::MODERN, 0, 600);
+    let mut cpu = CPU::new();
+    let other = include_bytes!("../../roms/INVADERS");
+    assert_eq!(movie.start(&mut cpu, other), Err(StateError::RomMismatch));
+    assert_eq!(movie.rom_hash, config::hash_rom(rom));
+
+    let data = movie.to_bytes();
+    assert_eq!(Movie::from_bytes(&data[..10]), Err(StateError::Truncated));
+    assert_eq!(Movie::from_bytes(b"C8SS"), Err(StateError::BadMagic));
+    // The movies of the first version didn't hold the speed, so they can't be played back identically
+    let mut old = data.clone();
+    old[4] = 1;
+    assert_eq!(
+        Movie::from_bytes(&old),
+        Err(StateError::UnsupportedVersion { version: 1 })
+    );
+    assert_eq!(Movie::from_bytes(&data).unwrap().ips, 600);
+
+    // The keys pressed once the last frame is over are dropped when saving
+    let mut movie = movie;
+    movie.record(5, true);
+    movie.end_frame();
+    movie.record(5, false);
+    movie.record(6, true);
+    let parsed = Movie::from_bytes(&movie.to_bytes()).unwrap();
+    assert_eq!(parsed.frames(), 1);
+    assert_eq!(
+        parsed.events(),
+        [KeyEvent {
+            frame: 0,
+            key: 5,
+            pressed: true
+        }]
+    );
+}
@@ -0,0 +1,162 @@
+use crate::{Instruction, CPU};
+use std::collections::VecDeque;
+use std::fmt::Write as _;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::ops::Range;
+use std::path::Path;
+
+// Width the mnemonics are padded to, so that the registers line up from one line to the next
+const MNEMONIC_WIDTH: usize = 20;
+
+// Where the lines of the trace go
+enum Sink {
+    // The latest lines, the oldest ones being dropped first
+    Ring {
+        lines: VecDeque<String>,
+        capacity: usize,
+    },
+    Writer(Box<dyn Write>),
+}
+
+// A trace of the instructions run by a CPU, one line per instruction, given to `CPU::set_trace`
+//
+// Each line holds the address and OP Code of the instruction, its mnemonic, and the registers before it is run:
+// 0x204 6C3F LD VC, 0x3F          V0=00 V1=00 ... VF=00 I=0000 SP=0 DT=00 ST=00
+// The format is the same from one run to the next, so that two traces can be compared with a diff
+pub struct Trace {
+    sink: Sink,
+    filter: Option<Range<u16>>,
+    error: Option<io::Error>, // First error writing the trace, after which nothing else is written
+}
+
+impl Trace {
+    // Keep the latest `capacity` lines in memory, to look back at what led to a crash for instance
+    pub fn ring(capacity: usize) -> Self {
+        Self::with_sink(Sink::Ring {
+            lines: VecDeque::with_capacity(capacity),
+            capacity: capacity.max(1),
+        })
+    }
+
+    // Write every line to a file, which is created or truncated
+    pub fn to_file(path: impl AsRef<Path>) -> io::Result<Self> {
+        let file = File::create(path)?;
+        Ok(Self::to_writer(Box::new(BufWriter::new(file))))
+    }
+
+    // Write every line to the writer, such as `io::stdout()`
+    pub fn to_writer(writer: Box<dyn Write>) -> Self {
+        Self::with_sink(Sink::Writer(writer))
+    }
+
+    fn with_sink(sink: Sink) -> Self {
+        Trace {
+            sink,
+            filter: None,
+            error: None,
+        }
+    }
+
+    // Only trace the instructions within the range of addresses, to leave out a busy loop for instance
+    pub fn with_filter(mut self, range: Range<u16>) -> Self {
+        self.filter = Some(range);
+        self
+    }
+
+    // The lines kept by a ring trace, the oldest one first
+    // The traces written elsewhere don't keep any line
+    pub fn lines(&self) -> impl Iterator<Item = &str> {
+        let lines = match &self.sink {
+            Sink::Ring { lines, .. } => Some(lines.iter().map(String::as_str)),
+            Sink::Writer(_) => None,
+        };
+        lines.into_iter().flatten()
+    }
+
+    // Flush the lines written so far, and report the first error writing them if there was any
+    pub fn flush(&mut self) -> io::Result<()> {
+        if let Some(err) = self.error.take() {
+            return Err(err);
+        }
+        match &mut self.sink {
+            Sink::Ring { .. } => Ok(()),
+            Sink::Writer(writer) => writer.flush(),
+        }
+    }
+
+    // The line of the instruction at the PC, as it would be traced, or None if the PC is past the end of the RAM
+    pub fn line(cpu: &CPU) -> Option<String> {
+        format_line(cpu)
+    }
+
+    // Called by `CPU::tick` before the instruction at the PC is run
+    pub(crate) fn record(&mut self, cpu: &CPU) {
+        let pc = cpu.pc();
+        if self.error.is_some()
+            || self
+                .filter
+                .as_ref()
+                .is_some_and(|range| !range.contains(&pc))
+        {
+            return;
+        }
+        let line = match format_line(cpu) {
+            Some(line) => line,
+            None => return,
+        };
+        match &mut self.sink {
+            Sink::Ring { lines, capacity } => {
+                if lines.len() == *capacity {
+                    lines.pop_front();
+                }
+                lines.push_back(line);
+            }
+            Sink::Writer(writer) => {
+                if let Err(err) = writeln!(writer, "{}", line) {
+                    self.error = Some(err);
+                }
+            }
+        }
+    }
+}
+
+// The line of the instruction at the PC, or None if the PC is past the end of the RAM
+fn format_line(cpu: &CPU) -> Option<String> {
+    let memory = cpu.memory();
+    let pc = cpu.pc() as usize;
+    let word =
+        |addr: usize| Some(((*memory.get(addr)? as u16) << 8) | *memory.get(addr + 1)? as u16);
+    let op = word(pc)?;
+
+    let mut line = String::new();
+    let mnemonic = match Instruction::decode(op) {
+        // The address of the XO-CHIP long load is the next 2 bytes of the program
+        Some(Instruction::LdILong) => {
+            let addr = word(pc + 2).unwrap_or(0);
+            let _ = write!(line, "{:#05X} {:04X}{:04X} ", pc, op, addr);
+            format!("LD I, LONG {:#06X}", addr)
+        }
+        Some(instruction) => {
+            let _ = write!(line, "{:#05X} {:04X}     ", pc, op);
+            instruction.to_string()
+        }
+        None => {
+            let _ = write!(line, "{:#05X} {:04X}     ", pc, op);
+            "???".to_string()
+        }
+    };
+    let _ = write!(line, "{:width$}", mnemonic, width = MNEMONIC_WIDTH);
+    for (x, value) in cpu.v_reg().iter().enumerate() {
+        let _ = write!(line, " V{:X}={:02X}", x, value);
+    }
+    let _ = write!(
+        line,
+        " I={:04X} SP={:X} DT={:02X} ST={:02X}",
+        cpu.i_reg(),
+        cpu.sp(),
+        cpu.dt(),
+        cpu.st()
+    );
+    Some(line)
+}
@@ -0,0 +1,239 @@
+use crate::{Trace, CPU};
+use std::collections::VecDeque;
+use std::fmt;
+
+// The first instruction at which two runs of a program differ
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Divergence {
+    // Number of the instruction in the traces, from 0
+    pub index: usize,
+    // What differs, such as `V3 0x0A != 0x0B`
+    pub differences: Vec<String>,
+    // The lines of the first trace leading up to the divergence, which are the same in the second one
+    pub before: Vec<String>,
+    // The lines of each trace from the divergence onwards, which are empty where a trace ends
+    pub a: Vec<String>,
+    pub b: Vec<String>,
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "First divergence at instruction {}: {}",
+            self.index,
+            self.differences.join(", ")
+        )?;
+        for line in &self.before {
+            writeln!(f, "    {}", line)?;
+        }
+        for (name, lines) in [("A", &self.a), ("B", &self.b)] {
+            if lines.is_empty() {
+                writeln!(f, "{} > (end of the trace)", name)?;
+            }
+            for (idx, line) in lines.iter().enumerate() {
+                let marker = if idx == 0 { '>' } else { ' ' };
+                writeln!(f, "{} {} {}", name, marker, line)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// Compare two traces in the format written by `Trace`, line by line
+// Only the address, the OP Code and the `NAME=VALUE` fields are compared, so the traces of another emulator only need
+// to be in the same format with the same values, and the fields missing from one of them are ignored
+// The traces don't hold the RAM or the screen, which are left out of the comparison (unlike in `diff_cpus`)
+// Empty lines, and lines starting with a #, are skipped
+pub fn diff_traces(a: &str, b: &str, context: usize) -> Option<Divergence> {
+    let lines = |text: &str| -> Vec<String> {
+        text.lines()
+            .map(str::trim_end)
+            .filter(|line| !line.trim().is_empty() && !line.starts_with('#'))
+            .map(String::from)
+            .collect()
+    };
+    let (a, b) = (lines(a), lines(b));
+
+    for index in 0..a.len().max(b.len()) {
+        let differences = match (a.get(index), b.get(index)) {
+            (Some(line_a), Some(line_b)) => compare_lines(line_a, line_b),
+            (Some(_), None) => vec!["B ends first".to_string()],
+            _ => vec!["A ends first".to_string()],
+        };
+        if !differences.is_empty() {
+            let after = |lines: &[String]| {
+                lines
+                    .iter()
+                    .skip(index)
+                    .take(context + 1)
+                    .cloned()
+                    .collect()
+            };
+            return Some(Divergence {
+                index,
+                differences,
+                before: a[index.saturating_sub(context)..index].to_vec(),
+                a: after(&a),
+                b: after(&b),
+            });
+        }
+    }
+    None
+}
+
+// Run two CPUs with the same program side by side, for instance with two sets of quirks, and compare them after every
+// instruction: the registers through their trace lines, and the whole RAM and screen
+// The CPUs must be loaded with the program already, and their timers are ticked every `ticks_per_frame` instructions
+pub fn diff_cpus(
+    a: &mut CPU,
+    b: &mut CPU,
+    ticks: usize,
+    ticks_per_frame: usize,
+    context: usize,
+) -> Option<Divergence> {
+    let mut before = VecDeque::with_capacity(context + 1);
+    for index in 0..ticks {
+        let (line_a, line_b) = (next_line(a), next_line(b));
+        let result_a = tick(a, index, ticks_per_frame);
+        let result_b = tick(b, index, ticks_per_frame);
+
+        let mut differences = match (&line_a, &line_b) {
+            (Some(line_a), Some(line_b)) => compare_lines(line_a, line_b),
+            (Some(_), None) => vec!["B has stopped".to_string()],
+            (None, Some(_)) => vec!["A has stopped".to_string()],
+            (None, None) => return None,
+        };
+        if differences.is_empty() {
+            differences = compare_cpus(a, b);
+        }
+        if result_a != result_b {
+            let describe = |result: Result<(), String>| result.err().unwrap_or("ok".to_string());
+            differences.push(format!("{} != {}", describe(result_a), describe(result_b)));
+        }
+
+        if !differences.is_empty() {
+            // Run each CPU a little further, for context
+            let after = |cpu: &mut CPU, line: Option<String>| {
+                let mut lines: Vec<_> = line.into_iter().collect();
+                for extra in 1..=context {
+                    match next_line(cpu) {
+                        Some(line) if tick(cpu, index + extra, ticks_per_frame).is_ok() => {
+                            lines.push(line)
+                        }
+                        Some(line) => {
+                            lines.push(line);
+                            break;
+                        }
+                        None => break,
+                    }
+                }
+                lines
+            };
+            return Some(Divergence {
+                index,
+                differences,
+                before: before.into_iter().collect(),
+                a: after(a, line_a),
+                b: after(b, line_b),
+            });
+        }
+
+        if context > 0 {
+            if before.len() == context {
+                before.pop_front();
+            }
+            before.extend(line_a);
+        }
+    }
+    None
+}
+
+// The line of the instruction the CPU is about to run, or None if it has stopped
+fn next_line(cpu: &CPU) -> Option<String> {
+    if cpu.is_halted() {
+        return None;
+    }
+    Trace::line(cpu)
+}
+
+// Run one instruction, and the timers at the end of every frame
+fn tick(cpu: &mut CPU, index: usize, ticks_per_frame: usize) -> Result<(), String> {
+    let result = cpu.tick().map_err(|err| err.to_string());
+    if (index + 1).is_multiple_of(ticks_per_frame.max(1)) {
+        cpu.tick_timers();
+    }
+    result
+}
+
+// The fields of a line which differ, such as `V3 0x0A != 0x0B`
+fn compare_lines(a: &str, b: &str) -> Vec<String> {
+    let (fields_a, fields_b) = (fields(a), fields(b));
+    let mut differences = Vec::new();
+    for (name, value_a) in &fields_a {
+        let value_b = fields_b.iter().find(|(other, _)| other == name);
+        if let Some((_, value_b)) = value_b {
+            if value_a != value_b {
+                differences.push(format!("{} {:#04X} != {:#04X}", name, value_a, value_b));
+            }
+        }
+    }
+    differences
+}
+
+// The address, the OP Code and the `NAME=VALUE` fields of a line, whose values are all hexadecimal
+// The mnemonic in between is left out, as emulators don't all spell them the same way
+fn fields(line: &str) -> Vec<(String, u64)> {
+    let hex = |text: &str| {
+        let text = text.trim_start_matches("0x").trim_start_matches("0X");
+        u64::from_str_radix(text, 16).ok()
+    };
+    let mut words = line.split_whitespace();
+    let mut fields = Vec::new();
+    for name in ["PC", "OP"] {
+        if let Some(value) = words.next().and_then(hex) {
+            fields.push((name.to_string(), value));
+        }
+    }
+    for word in words {
+        if let Some((name, value)) = word.split_once('=') {
+            if let Some(value) = hex(value) {
+                fields.push((name.to_ascii_uppercase(), value));
+            }
+        }
+    }
+    fields
+}
+
+// The differences in the RAM and on the screen, after the same instruction was run on both CPUs
+fn compare_cpus(a: &CPU, b: &CPU) -> Vec<String> {
+    let mut differences = Vec::new();
+    let (memory_a, memory_b) = (a.memory(), b.memory());
+    if let Some(addr) =
+        (0..memory_a.len().min(memory_b.len())).find(|&addr| memory_a[addr] != memory_b[addr])
+    {
+        differences.push(format!(
+            "RAM[{:#05X}] {:#04X} != {:#04X}",
+            addr, memory_a[addr], memory_b[addr]
+        ));
+    }
+
+    let (display_a, display_b) = (a.get_display(), b.get_display());
+    if (display_a.width, display_a.height) != (display_b.width, display_b.height) {
+        differences.push(format!(
+            "screen {}x{} != {}x{}",
+            display_a.width, display_a.height, display_b.width, display_b.height
+        ));
+    } else {
+        let pixels = display_a
+            .pixels
+            .iter()
+            .zip(display_b.pixels)
+            .filter(|(pixel_a, pixel_b)| pixel_a != pixel_b)
+            .count();
+        if pixels > 0 {
+            differences.push(format!("screen {} pixels differ", pixels));
+        }
+    }
+    differences
+}
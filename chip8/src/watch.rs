@@ -0,0 +1,143 @@
+use std::fmt;
+use std::ops::Range;
+
+// What a watchpoint looks at
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WatchTarget {
+    // The instructions reading from the addresses (DXYN, FX65, F002, ...), but not the fetching of the instructions
+    Read(Range<usize>),
+    // The instructions writing to the addresses (FX33, FX55, ...), even when the value written is the same
+    Write(Range<usize>),
+    // The V register, when an instruction changes its value
+    V(usize),
+    // The I register, when an instruction changes its value
+    I,
+}
+
+// What happens when a watchpoint is hit
+pub enum WatchAction {
+    // The instruction completes, and the CPU does nothing from then on until `CPU::take_watch_hit` is called
+    Halt,
+    // The callback is called with the hit, and the program goes on
+    Callback(Box<dyn FnMut(&WatchHit)>),
+}
+
+// The access which hit a watchpoint
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HitKind {
+    Read { addr: usize },
+    Write { addr: usize },
+    V(usize),
+    I,
+}
+
+// A watchpoint being hit by the instruction at `pc`
+// A read has the same old and new values, which is the value read
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WatchHit {
+    pub id: usize,
+    pub pc: u16,
+    pub kind: HitKind,
+    pub old: u16,
+    pub new: u16,
+}
+
+impl fmt::Display for WatchHit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Watchpoint {} hit at {:#05X}: ", self.id, self.pc)?;
+        match self.kind {
+            HitKind::Read { addr } => write!(f, "read {:#04X} from {:#05X}", self.new, addr),
+            HitKind::Write { addr } => write!(
+                f,
+                "write to {:#05X}, {:#04X} -> {:#04X}",
+                addr, self.old, self.new
+            ),
+            HitKind::V(x) => write!(f, "V{:X} {:#04X} -> {:#04X}", x, self.old, self.new),
+            HitKind::I => write!(f, "I {:#05X} -> {:#05X}", self.old, self.new),
+        }
+    }
+}
+
+// The watchpoints of a CPU, which are checked on every read and write of the RAM and after every instruction
+// Nothing is checked at all while there are none, so they don't slow the emulation down unless they are used
+#[derive(Default)]
+pub(crate) struct Watchpoints {
+    watchpoints: Vec<(usize, WatchTarget, WatchAction)>,
+    next_id: usize,
+    pub(crate) pc: u16,               // Address of the instruction being run
+    pub(crate) hit: Option<WatchHit>, // The hit which halted the program
+}
+
+impl Watchpoints {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.watchpoints.is_empty()
+    }
+
+    pub(crate) fn add(&mut self, target: WatchTarget, action: WatchAction) -> usize {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.watchpoints.push((id, target, action));
+        id
+    }
+
+    pub(crate) fn remove(&mut self, id: usize) -> bool {
+        let len = self.watchpoints.len();
+        self.watchpoints.retain(|(other, _, _)| *other != id);
+        self.watchpoints.len() != len
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.watchpoints.clear();
+        self.hit = None;
+    }
+
+    pub(crate) fn read(&mut self, addr: usize, value: u8) {
+        self.check(HitKind::Read { addr }, value as u16, value as u16);
+    }
+
+    pub(crate) fn write(&mut self, addr: usize, old: u8, new: u8) {
+        self.check(HitKind::Write { addr }, old as u16, new as u16);
+    }
+
+    // Compare the registers from before and after the instruction
+    pub(crate) fn registers(&mut self, old_v: &[u8], old_i: u16, new_v: &[u8], new_i: u16) {
+        for (x, (&old, &new)) in old_v.iter().zip(new_v).enumerate() {
+            if old != new {
+                self.check(HitKind::V(x), old as u16, new as u16);
+            }
+        }
+        if old_i != new_i {
+            self.check(HitKind::I, old_i, new_i);
+        }
+    }
+
+    fn check(&mut self, kind: HitKind, old: u16, new: u16) {
+        for (id, target, action) in self.watchpoints.iter_mut() {
+            let matches = match (&*target, kind) {
+                (WatchTarget::Read(range), HitKind::Read { addr }) => range.contains(&addr),
+                (WatchTarget::Write(range), HitKind::Write { addr }) => range.contains(&addr),
+                (WatchTarget::V(x), HitKind::V(y)) => *x == y,
+                (WatchTarget::I, HitKind::I) => true,
+                _ => false,
+            };
+            if !matches {
+                continue;
+            }
+
+            let hit = WatchHit {
+                id: *id,
+                pc: self.pc,
+                kind,
+                old,
+                new,
+            };
+            match action {
+                // Only the first hit is kept when an instruction hits several watchpoints
+                WatchAction::Halt => {
+                    self.hit.get_or_insert(hit);
+                }
+                WatchAction::Callback(callback) => callback(&hit),
+            }
+        }
+    }
+}
@@ -0,0 +1,315 @@
+// Runs ROMs headlessly and compares their final screen against the golden images in tests/golden
+//
+// Every ROM and golden image is checked in, and a missing one fails the test
+// Run with UPDATE_GOLDEN=1 to write the golden images again: the emulator is then compared against itself, so the new
+// images must be checked by hand (the screen of the game at that point, the keys having had their effect) before
+// being committed
+//
+// The conformance ROMs in roms/tests are assembled for the platform of the case, and draw a tick or a cross for each
+// of their checks (see the comment at the top of each source), which are also compared against the expected results
+// of the case: those come from the CHIP-8 specification and the documented behaviour of each interpreter, not from
+// this emulator
+use chip8::asm::assemble;
+use chip8::{Display, Platform, Quirks, CPU};
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+// The speed the database lists the games at, as their tickrate
+const TICKS_PER_FRAME: usize = 10;
+// Characters of the pixels in the golden images, indexed by the XO-CHIP planes they are set on
+const PIXELS: [char; 4] = ['.', '#', 'o', '@'];
+
+struct Case {
+    name: &'static str,
+    // Path of the ROM, from the root of the repository
+    rom: &'static str,
+    platform: Platform,
+    quirks: Quirks,
+    frames: u32,
+    // Keys pressed (true) or released (false) at the start of the given frames
+    keys: &'static [(u32, usize, bool)],
+    // Marks the conformance ROMs are expected to draw, in order: v for a tick and x for a cross
+    marks: &'static str,
+}
+
+// Every conformance check passes, whatever the quirks
+const ALL_PASS_OPCODES: &str = "vvvvvvvvvvvvvvvvvvvvvvvvvvv";
+const ALL_PASS_FLAGS: &str = "vvvvvvvvvvvvvvvvvvv";
+const ALL_PASS_KEYPAD: &str = "vvvvv";
+// Keys of the keypad ROM: 5 is pressed then released, then A is pressed
+const KEYPAD_KEYS: &[(u32, usize, bool)] = &[
+    (20, 0x5, true),
+    (30, 0x5, false),
+    (50, 0xA, true),
+    (55, 0xA, false),
+];
+
+const CASES: &[Case] = &[
+    Case {
+        name: "pong",
+        rom: "roms/PONG",
+        platform: Platform::Chip8,
+        // The platform and quirks the database lists the game with (see src/database.json)
+        quirks: Quirks::CHIP_48,
+        // The game starts after 96 frames, then the left paddle goes up from the middle to the top, and the frame
+        // ends after the right paddle is drawn
+        frames: 302,
+        keys: &[(120, 0x1, true), (135, 0x1, false)],
+        marks: "",
+    },
+    Case {
+        name: "invaders",
+        rom: "roms/INVADERS",
+        platform: Platform::Chip8,
+        quirks: Quirks::CHIP_48,
+        frames: 300,
+        keys: &[
+            (30, 0x5, true),
+            (40, 0x5, false),
+            (100, 0x4, true),
+            (130, 0x4, false),
+        ],
+        marks: "",
+    },
+    Case {
+        name: "opcodes",
+        rom: "roms/tests/opcodes.asm",
+        platform: Platform::Chip8,
+        quirks: Quirks::MODERN,
+        frames: 120,
+        keys: &[],
+        marks: ALL_PASS_OPCODES,
+    },
+    Case {
+        name: "opcodes-vip",
+        rom: "roms/tests/opcodes.asm",
+        platform: Platform::Chip8,
+        quirks: Quirks::COSMAC_VIP,
+        frames: 120,
+        keys: &[],
+        marks: ALL_PASS_OPCODES,
+    },
+    Case {
+        name: "opcodes-schip",
+        rom: "roms/tests/opcodes.asm",
+        platform: Platform::SuperChip,
+        quirks: Quirks::SUPER_CHIP,
+        frames: 120,
+        keys: &[],
+        marks: ALL_PASS_OPCODES,
+    },
+    Case {
+        name: "opcodes-xochip",
+        rom: "roms/tests/opcodes.asm",
+        platform: Platform::XoChip,
+        quirks: Quirks::XO_CHIP,
+        frames: 120,
+        keys: &[],
+        marks: ALL_PASS_OPCODES,
+    },
+    Case {
+        name: "flags",
+        rom: "roms/tests/flags.asm",
+        platform: Platform::Chip8,
+        quirks: Quirks::MODERN,
+        frames: 120,
+        keys: &[],
+        marks: ALL_PASS_FLAGS,
+    },
+    Case {
+        name: "flags-vip",
+        rom: "roms/tests/flags.asm",
+        platform: Platform::Chip8,
+        quirks: Quirks::COSMAC_VIP,
+        frames: 120,
+        keys: &[],
+        marks: ALL_PASS_FLAGS,
+    },
+    Case {
+        name: "flags-schip",
+        rom: "roms/tests/flags.asm",
+        platform: Platform::SuperChip,
+        quirks: Quirks::SUPER_CHIP,
+        frames: 120,
+        keys: &[],
+        marks: ALL_PASS_FLAGS,
+    },
+    Case {
+        name: "flags-xochip",
+        rom: "roms/tests/flags.asm",
+        platform: Platform::XoChip,
+        quirks: Quirks::XO_CHIP,
+        frames: 120,
+        keys: &[],
+        marks: ALL_PASS_FLAGS,
+    },
+    // The quirks of the modern preset are this emulator's own choice, those of the other presets are the ones listed
+    // for each interpreter by Timendus' CHIP-8 test suite: jumping, shifting, VF reset, memory, display wait, clipping
+    Case {
+        name: "quirks",
+        rom: "roms/tests/quirks.asm",
+        platform: Platform::Chip8,
+        quirks: Quirks::MODERN,
+        frames: 120,
+        keys: &[],
+        marks: "xvxxxx",
+    },
+    Case {
+        name: "quirks-vip",
+        rom: "roms/tests/quirks.asm",
+        platform: Platform::Chip8,
+        quirks: Quirks::COSMAC_VIP,
+        frames: 120,
+        keys: &[],
+        marks: "xxvvvv",
+    },
+    Case {
+        name: "quirks-schip",
+        rom: "roms/tests/quirks.asm",
+        platform: Platform::SuperChip,
+        quirks: Quirks::SUPER_CHIP,
+        frames: 120,
+        keys: &[],
+        marks: "vvxxvv",
+    },
+    Case {
+        name: "quirks-xochip",
+        rom: "roms/tests/quirks.asm",
+        platform: Platform::XoChip,
+        quirks: Quirks::XO_CHIP,
+        frames: 120,
+        keys: &[],
+        marks: "xxxvxx",
+    },
+    Case {
+        name: "keypad",
+        rom: "roms/tests/keypad.asm",
+        platform: Platform::Chip8,
+        quirks: Quirks::MODERN,
+        frames: 120,
+        keys: KEYPAD_KEYS,
+        marks: ALL_PASS_KEYPAD,
+    },
+    Case {
+        name: "keypad-vip",
+        rom: "roms/tests/keypad.asm",
+        platform: Platform::Chip8,
+        quirks: Quirks::COSMAC_VIP,
+        frames: 120,
+        keys: KEYPAD_KEYS,
+        marks: ALL_PASS_KEYPAD,
+    },
+    Case {
+        name: "keypad-schip",
+        rom: "roms/tests/keypad.asm",
+        platform: Platform::SuperChip,
+        quirks: Quirks::SUPER_CHIP,
+        frames: 120,
+        keys: KEYPAD_KEYS,
+        marks: ALL_PASS_KEYPAD,
+    },
+    Case {
+        name: "keypad-xochip",
+        rom: "roms/tests/keypad.asm",
+        platform: Platform::XoChip,
+        quirks: Quirks::XO_CHIP,
+        frames: 120,
+        keys: KEYPAD_KEYS,
+        marks: ALL_PASS_KEYPAD,
+    },
+];
+
+// Run the case and return the marks and the screen it ends with
+fn run(case: &Case) -> (String, String) {
+    let root = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("..");
+    let mut rom = fs::read(root.join(case.rom))
+        .unwrap_or_else(|err| panic!("{}: unable to read {}: {}", case.name, case.rom, err));
+    if case.rom.ends_with(".asm") {
+        let source = String::from_utf8(rom).unwrap();
+        rom =
+            assemble(&source, case.platform).unwrap_or_else(|err| panic!("{}: {}", case.name, err));
+    }
+
+    let mut chip8 = CPU::with_platform(case.platform);
+    chip8.set_quirks(case.quirks);
+    chip8.set_seed(0);
+    chip8.load(&rom).unwrap();
+    for frame in 0..case.frames {
+        for &(_, key, pressed) in case.keys.iter().filter(|key| key.0 == frame) {
+            chip8.keypress(key, pressed);
+        }
+        for _ in 0..TICKS_PER_FRAME {
+            if let Err(err) = chip8.tick() {
+                panic!("{}: {}", case.name, err);
+            }
+        }
+        chip8.tick_timers();
+    }
+    let display = chip8.get_display();
+    (marks(&display, case.marks.len()), screen(&display))
+}
+
+fn screen(display: &Display) -> String {
+    let mut screen = String::new();
+    for row in display.pixels.chunks(display.width) {
+        screen.extend(row.iter().map(|&pixel| PIXELS[pixel as usize]));
+        screen.push('\n');
+    }
+    screen
+}
+
+// Read the marks drawn by a conformance ROM, ten to a row from (2, 2), 6 pixels apart: the top right pixel is lit on
+// both, and the top left one only on a cross
+// A cell without a mark reads as -
+fn marks(display: &Display, count: usize) -> String {
+    let lit = |x: usize, y: usize| display.pixels[y * display.width + x] != 0;
+    (0..count)
+        .map(|cell| {
+            let (x, y) = (2 + cell % 10 * 6, 2 + cell / 10 * 6);
+            match (lit(x + 4, y), lit(x, y)) {
+                (true, false) => 'v',
+                (true, true) => 'x',
+                _ => '-',
+            }
+        })
+        .collect()
+}
+
+#[test]
+fn roms_match_golden_images() {
+    let update = env::var_os("UPDATE_GOLDEN").is_some();
+    let golden_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/golden");
+    let mut failures = Vec::new();
+
+    for case in CASES {
+        let (marks, screen) = run(case);
+        if marks != case.marks {
+            failures.push(format!(
+                "{} drew the marks {}, expected {}",
+                case.name, marks, case.marks
+            ));
+        }
+        let golden_path = golden_dir.join(format!("{}.txt", case.name));
+        if update {
+            fs::write(&golden_path, &screen).unwrap();
+            eprintln!("wrote {}, check it by hand", golden_path.display());
+            continue;
+        }
+        match fs::read_to_string(&golden_path) {
+            Ok(golden) if golden == screen => (),
+            Ok(golden) => failures.push(format!(
+                "{} differs from its golden image\nexpected:\n{}\nfound:\n{}",
+                case.name, golden, screen
+            )),
+            Err(err) => failures.push(format!(
+                "{}: unable to read {}: {}",
+                case.name,
+                golden_path.display(),
+                err
+            )),
+        }
+    }
+
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
@@ -0,0 +1,139 @@
+use chip8::config::Settings;
+use chip8::database::RomInfo;
+use chip8::Keymap;
+use sdl2::controller::{Button, GameController};
+use sdl2::GameControllerSubsystem;
+
+// The buttons of the gamepads, named as by SDL, bound to the keys most games use: 2, 4, 6 and 8 to move and 5 to fire
+const DEFAULT_BUTTONS: [(&str, usize); 12] = [
+    ("dpup", 0x2),
+    ("dpdown", 0x8),
+    ("dpleft", 0x4),
+    ("dpright", 0x6),
+    ("a", 0x5),
+    ("b", 0x0),
+    ("x", 0x1),
+    ("y", 0x3),
+    ("leftshoulder", 0x7),
+    ("rightshoulder", 0x9),
+    ("back", 0xA),
+    ("start", 0xF),
+];
+
+// The controls of the ROM database, and the buttons they are put on
+// The controls of the second player start with player2, such as player2Up
+const CONTROLS: [(&str, &str); 6] = [
+    ("up", "dpup"),
+    ("down", "dpdown"),
+    ("left", "dpleft"),
+    ("right", "dpright"),
+    ("a", "a"),
+    ("b", "b"),
+];
+
+// The gamepads plugged in, which press the CHIP-8 keys alongside the keyboard
+// The first one is played by the first player, and the other ones by the second player
+pub struct Gamepads {
+    subsystem: GameControllerSubsystem,
+    controllers: Vec<Gamepad>, // In the order they were plugged in
+    buttons: [Keymap; 2],      // Buttons of each player
+}
+
+struct Gamepad {
+    controller: GameController,
+    held: Vec<(Button, usize)>, // Buttons held down, with the CHIP-8 key each of them pressed
+}
+
+impl Gamepads {
+    // The buttons are bound to the controls of the ROM database first, then to the ones of the settings
+    pub fn new(
+        subsystem: GameControllerSubsystem,
+        info: Option<&RomInfo>,
+        settings: &Settings,
+    ) -> Self {
+        let buttons = [0, 1].map(|player| {
+            let mut buttons: Keymap = DEFAULT_BUTTONS.into_iter().collect();
+            if let Some(info) = info {
+                buttons.extend(&controls(info, player));
+            }
+            if let Some(overrides) = &settings.buttons {
+                buttons.extend(overrides);
+            }
+            buttons
+        });
+        Gamepads {
+            subsystem,
+            controllers: Vec::new(),
+            buttons,
+        }
+    }
+
+    // A gamepad was plugged in, or was already there when the game started
+    pub fn add(&mut self, joystick_index: u32) {
+        match self.subsystem.open(joystick_index) {
+            Ok(controller) => {
+                let player = self.controllers.len().min(1) + 1;
+                println!("Connected {} for player {}", controller.name(), player);
+                self.controllers.push(Gamepad {
+                    controller,
+                    held: Vec::new(),
+                });
+            }
+            Err(err) => eprintln!("Unable to open the gamepad: {}", err),
+        }
+    }
+
+    // The next gamepads move up, so that there is always a first player if there is a gamepad
+    // Returns the CHIP-8 keys the gamepad was holding down, which are to be released
+    pub fn remove(&mut self, instance_id: u32) -> Vec<usize> {
+        match self.position(instance_id) {
+            Some(idx) => {
+                let gamepad = self.controllers.remove(idx);
+                println!("Disconnected {}", gamepad.controller.name());
+                gamepad.held.into_iter().map(|(_, key)| key).collect()
+            }
+            None => Vec::new(),
+        }
+    }
+
+    // The CHIP-8 key pressed or released by a button of a gamepad
+    // A button releases the key it pressed, even if the gamepad has changed players since
+    pub fn press(&mut self, instance_id: u32, button: Button, pressed: bool) -> Option<usize> {
+        let idx = self.position(instance_id)?;
+        let held = &mut self.controllers[idx].held;
+        if !pressed {
+            let held_idx = held.iter().position(|&(other, _)| other == button)?;
+            return Some(held.remove(held_idx).1);
+        }
+        let key = self.buttons[idx.min(1)].key(&button.string())?;
+        held.push((button, key));
+        Some(key)
+    }
+
+    fn position(&self, instance_id: u32) -> Option<usize> {
+        self.controllers
+            .iter()
+            .position(|gamepad| gamepad.controller.instance_id() == instance_id)
+    }
+}
+
+// The buttons of a player bound to the controls of the ROM database
+// The second player gets the same ones as the first player, unless the game has controls of its own for them
+fn controls(info: &RomInfo, player: usize) -> Keymap {
+    let control = |name: &str| {
+        let key = |control: &str| {
+            info.keys
+                .iter()
+                .find(|(other, _)| other.eq_ignore_ascii_case(control))
+                .map(|&(_, key)| key)
+        };
+        match player {
+            0 => key(name),
+            _ => key(&format!("player2{}", name)).or_else(|| key(name)),
+        }
+    };
+    CONTROLS
+        .into_iter()
+        .filter_map(|(name, button)| control(name).map(|key| (button, key)))
+        .collect()
+}
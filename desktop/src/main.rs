@@ -1,51 +1,241 @@
+use chip8::config::{self, Config, Settings};
+use chip8::database;
+use chip8::debugger::{Action, Debugger};
 use chip8::*;
+use gamepad::Gamepads;
+use options::*;
+use sdl2::audio::{AudioCallback, AudioDevice, AudioSpecDesired};
 use sdl2::event::Event;
-use sdl2::keyboard::Keycode;
+use sdl2::keyboard::{Keycode, Scancode};
 use sdl2::pixels::Color;
 use sdl2::rect::Rect;
 use sdl2::render::Canvas;
 use sdl2::video::Window;
 use std::env;
-use std::fs::File;
-use std::io::Read;
+use std::fs;
+use std::io::{self, BufRead, ErrorKind, Write};
+use std::path::Path;
+use std::process;
+use std::time::{SystemTime, UNIX_EPOCH};
 
-// STYLISTIC PREFERENCES so that the game appears properly on the Emulation
-const SCALE: u32 = 15;
-const WINDOW_WIDTH: u32 = (SCREEN_WIDTH as u32) * SCALE;
-const WINDOW_HEIGHT: u32 = (SCREEN_HEIGHT as u32) * SCALE;
-const TICKS_PER_FRAME: usize = 10;
+mod gamepad;
+mod options;
+
+// The instructions per second set with --ips are spread over the frames, 60 times per second
+const FRAMES_PER_SECOND: u64 = 60;
+// Holding BACKSPACE rewinds the game, up to 10 seconds back at 60 FPS
+const REWIND_CAPACITY: usize = 300;
+const REWIND_INTERVAL: usize = 2;
+// Colors of the pixels drawn on the second XO-CHIP plane alone, and on both planes
+// The background and the first plane, which plain CHIP-8 and SUPER-CHIP programs draw on, are set with --bg and --fg
+const PLANE_2_COLOR: Color = Color::RGB(170, 170, 170);
+const BOTH_PLANES_COLOR: Color = Color::RGB(85, 85, 85);
+const SAMPLE_RATE: i32 = 44100;
+
+// The sound of the buzzer, rendered by the emulator on the audio thread
+struct Buzzer(Audio);
+
+impl AudioCallback for Buzzer {
+    type Channel = f32;
+
+    fn callback(&mut self, out: &mut [f32]) {
+        self.0.fill(out);
+    }
+}
 
 // Main code
 fn main() {
-    let args: Vec<_> = env::args().collect();
-    /*
-        Just accept 1 argument
-        The actual path to the source file containing the code of the game
-    */
-    if args.len() != 2 {
-        println!("Usage: cargo run path/to/game");
+    // See the usage in options.rs for the list of the options
+    if env::args()
+        .skip(1)
+        .any(|arg| arg == "--help" || arg == "-h")
+    {
+        println!("{}", USAGE);
         return;
     }
+    let options = Options::parse(env::args().skip(1)).unwrap_or_else(|err| fail(&err));
+    let record_path = options.record_path;
+
+    // Try to open the file, which is loaded into the chip8's RAM once the platform is known
+    let buffer = fs::read(&options.rom_path)
+        .unwrap_or_else(|err| fail(&format!("Unable to read {}: {}", options.rom_path, err)));
+
+    // The settings given on the command line override the ones of the config file, and are saved into it on request
+    let config_path = options.config_path.clone().unwrap_or_else(|| {
+        let dir = Path::new(&options.rom_path)
+            .parent()
+            .unwrap_or(Path::new(""));
+        dir.join("chip8.toml").to_string_lossy().into_owned()
+    });
+    let mut config = match fs::read_to_string(&config_path) {
+        Ok(text) => Config::parse(&text).unwrap_or_else(|err| {
+            fail(&format!(
+                "Unable to read the config {}: {}",
+                config_path, err
+            ))
+        }),
+        Err(err) if err.kind() == ErrorKind::NotFound => Config::default(),
+        Err(err) => fail(&format!(
+            "Unable to read the config {}: {}",
+            config_path, err
+        )),
+    };
+    let rom_hash = config::hash_rom(&buffer);
+    let settings = config.settings(&rom_hash).merge(&options.settings);
+    if options.save_settings {
+        let name = Path::new(&options.rom_path).file_name();
+        let changes = Settings {
+            name: name.map(|name| name.to_string_lossy().into_owned()),
+            ..options.settings.clone()
+        };
+        config.update(&rom_hash, &changes);
+        match fs::write(&config_path, config.to_toml()) {
+            Ok(()) => println!("Saved the settings to {}", config_path),
+            Err(err) => fail(&format!(
+                "Unable to save the settings to {}: {}",
+                config_path, err
+            )),
+        }
+    }
+
+    let platform = settings.platform().unwrap_or_default();
+    let mut ips = settings.ips.unwrap_or(DEFAULT_IPS);
+    let scale = settings.scale.unwrap_or(DEFAULT_SCALE);
+    let [r, g, b] = settings.background().unwrap_or(DEFAULT_BACKGROUND);
+    let background = Color::RGB(r, g, b);
+    let [r, g, b] = settings.foreground().unwrap_or(DEFAULT_FOREGROUND);
+    let foreground = Color::RGB(r, g, b);
+    let palette = [background, foreground, PLANE_2_COLOR, BOTH_PLANES_COLOR];
+    let keymap = settings.keymap();
 
     // Create the emulator
-    let mut chip8 = CPU::new();
-    print!("{}", &args[1]);
+    let mut chip8 = CPU::with_platform(platform);
+    if let Some(quirks) = settings.quirks() {
+        chip8.set_quirks(quirks);
+    }
+    if let Some(seed) = options.seed {
+        chip8.set_seed(seed);
+    }
+    println!("{}", options.rom_path);
+    // The games of the ROM database are shown with their title and controls
+    let info = database::lookup(&rom_hash);
+    if let Some(info) = &info {
+        if info.authors.is_empty() {
+            println!("{}", info.title);
+        } else {
+            println!("{} by {}", info.title, info.authors.join(", "));
+        }
+        if !info.keys.is_empty() {
+            println!("Controls: {}", info.controls());
+        }
+    }
+    if let Err(err) = chip8.load(&buffer) {
+        fail(&format!("Unable to load {}: {}", options.rom_path, err));
+    }
 
-    // Try to open the file and then load it into the chip8's RAM
-    let mut rom = File::open(&args[1]).expect("Unable to open file");
-    let mut buffer = Vec::new();
-    rom.read_to_end(&mut buffer).unwrap();
-    chip8.load(&buffer);
+    // A recorded session starts from a fresh CPU with a random seed (unless one is given), which is saved in the movie
+    let mut recording = record_path.as_ref().map(|_| {
+        let seed = options.seed.unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |time| time.as_nanos() as u64)
+        });
+        let movie = Movie::new(&buffer, platform, chip8.quirks(), seed, ips);
+        movie
+            .start(&mut chip8, &buffer)
+            .expect("The movie is made for this ROM");
+        movie
+    });
+
+    // A played back session replaces the platform, quirks, seed and speed with the ones of the movie
+    let mut playing = match &options.play_path {
+        Some(path) => {
+            let movie = fs::read(path)
+                .map_err(|err| err.to_string())
+                .and_then(|data| Movie::from_bytes(&data).map_err(|err| err.to_string()))
+                .and_then(|movie| {
+                    movie
+                        .start(&mut chip8, &buffer)
+                        .map_err(|err| err.to_string())?;
+                    Ok(movie)
+                });
+            match movie {
+                Ok(movie) => {
+                    if movie.ips != ips {
+                        println!(
+                            "Playing at {} instructions per second, as recorded",
+                            movie.ips
+                        );
+                        ips = movie.ips;
+                    }
+                    Some(movie)
+                }
+                Err(err) => fail(&format!("Unable to play {}: {}", path, err)),
+            }
+        }
+        None => None,
+    };
+    let mut frame = 0;
+    // Instructions left over from the previous frames, in sixtieths of an instruction
+    let mut tick_budget = 0;
+    // F2 pauses and resumes the game
+    let mut paused = options.paused;
+
+    // The quick-save slot is kept next to the ROM
+    let save_path = format!("{}.sav", options.rom_path);
+    let mut rewind = Rewind::new(REWIND_CAPACITY, REWIND_INTERVAL);
+    // Created the first time F10 is pressed, after which its breakpoints and watches are checked before every instruction
+    let mut debugger: Option<Debugger> = None;
 
     // Setup SDL (Boilerplate Code)
     let sdl_context = sdl2::init().unwrap();
     let video_subsystem = sdl_context.video().unwrap();
-    let window = video_subsystem
-        .window("Chip-8 Emulator", WINDOW_WIDTH, WINDOW_HEIGHT)
-        .position_centered()
-        .opengl()
-        .build()
-        .unwrap();
+    // The window has the size of the lo-res screen scaled up, and the hi-res screen is drawn at half the scale
+    let mut window = video_subsystem.window(
+        info.as_ref().map_or("Chip-8 Emulator", |info| &info.title),
+        SCREEN_WIDTH as u32 * scale,
+        SCREEN_HEIGHT as u32 * scale,
+    );
+    window.position_centered().opengl();
+    if options.fullscreen {
+        window.fullscreen_desktop();
+    }
+    let window = window.build().unwrap();
+
+    // The game can still be played without any sound if there is no audio device
+    let audio = sdl_context.audio().and_then(|audio_subsystem| {
+        let spec = AudioSpecDesired {
+            freq: Some(SAMPLE_RATE),
+            channels: Some(1),
+            samples: None,
+        };
+        audio_subsystem.open_playback(None, &spec, |spec| {
+            let mut audio = Audio::new(spec.freq as u32);
+            audio.set_volume(settings.volume.unwrap_or(DEFAULT_VOLUME) as f32 / 100.0);
+            audio.set_tone(settings.pitch.unwrap_or(DEFAULT_PITCH));
+            Buzzer(audio)
+        })
+    });
+    let mut audio = match audio {
+        Ok(audio) => Some(audio),
+        Err(err) => {
+            eprintln!(
+                "Unable to open the audio device, the game will be silent: {}",
+                err
+            );
+            None
+        }
+    };
+
+    // The gamepads are opened as they are plugged in, including the ones which already are, and the game can still be
+    // played with the keyboard alone without them
+    let mut gamepads = match sdl_context.game_controller() {
+        Ok(subsystem) => Some(Gamepads::new(subsystem, info.as_ref(), &settings)),
+        Err(err) => {
+            eprintln!("Unable to use the gamepads: {}", err);
+            None
+        }
+    };
 
     // Create a canvas to draw on and reset the same to blank
     let mut canvas = window.into_canvas().present_vsync().build().unwrap();
@@ -67,21 +257,115 @@ fn main() {
                     break 'gameloop;
                 }
 
+                // F5 saves the state of the game to the quick-save slot
+                Event::KeyDown {
+                    keycode: Some(Keycode::F5),
+                    repeat: false,
+                    ..
+                } => match fs::write(&save_path, chip8.save_state()) {
+                    Ok(()) => println!("Saved the state to {}", save_path),
+                    Err(err) => eprintln!("Unable to save the state: {}", err),
+                },
+
+                // F2 pauses and resumes the game, without the debugger
+                Event::KeyDown {
+                    keycode: Some(Keycode::F2),
+                    repeat: false,
+                    ..
+                } => {
+                    paused = !paused;
+                    println!("{}", if paused { "Paused" } else { "Resumed" });
+                }
+
+                // Restoring a state, rewinding or debugging would make the movie diverge from the session
+                Event::KeyDown {
+                    keycode: Some(Keycode::F9 | Keycode::F10 | Keycode::Backspace),
+                    repeat: false,
+                    ..
+                } if recording.is_some() || playing.is_some() => {
+                    eprintln!("Loading states, rewinding and debugging are disabled during movies");
+                }
+
+                // F9 restores the state of the game from the quick-save slot
+                Event::KeyDown {
+                    keycode: Some(Keycode::F9),
+                    repeat: false,
+                    ..
+                } => match fs::read(&save_path) {
+                    Ok(state) => match chip8.load_state(&state) {
+                        Ok(()) => {
+                            rewind.clear();
+                            println!("Loaded the state from {}", save_path);
+                        }
+                        Err(err) => eprintln!("Unable to load the state: {}", err),
+                    },
+                    Err(err) => eprintln!("Unable to read {}: {}", save_path, err),
+                },
+
+                // F10 pauses the game and hands the control over to the debugger, in the terminal
+                Event::KeyDown {
+                    keycode: Some(Keycode::F10),
+                    repeat: false,
+                    ..
+                } => {
+                    let debugger = debugger.get_or_insert_with(Debugger::new);
+                    pause_sound(&audio);
+                    println!("Paused, type help for the list of commands and continue to resume");
+                    if !debug_prompt(&mut chip8, debugger, &mut canvas, &palette) {
+                        break 'gameloop;
+                    }
+                }
+
                 // If a key is pressed, set the same to be pressed in the CPU
                 Event::KeyDown {
-                    keycode: Some(key), ..
+                    scancode: Some(scancode),
+                    ..
                 } => {
-                    if let Some(k) = key2btn(key) {
-                        chip8.keypress(k, true);
+                    if let Some(k) = key_code(scancode).and_then(|code| keymap.key(&code)) {
+                        press_key(&mut chip8, &mut recording, &playing, k, true);
                     }
                 }
 
                 // If a key is released, set the same to be released in the CPU
                 Event::KeyUp {
-                    keycode: Some(key), ..
+                    scancode: Some(scancode),
+                    ..
                 } => {
-                    if let Some(k) = key2btn(key) {
-                        chip8.keypress(k, false);
+                    if let Some(k) = key_code(scancode).and_then(|code| keymap.key(&code)) {
+                        press_key(&mut chip8, &mut recording, &playing, k, false);
+                    }
+                }
+
+                // The gamepads can be plugged in and out while playing
+                Event::ControllerDeviceAdded { which, .. } => {
+                    if let Some(gamepads) = &mut gamepads {
+                        gamepads.add(which);
+                    }
+                }
+                // A gamepad unplugged with buttons held down releases their keys, as it won't send the button ups
+                Event::ControllerDeviceRemoved { which, .. } => {
+                    if let Some(gamepads) = &mut gamepads {
+                        for k in gamepads.remove(which) {
+                            press_key(&mut chip8, &mut recording, &playing, k, false);
+                        }
+                    }
+                }
+
+                // The buttons of the gamepads press the keys along with the keyboard
+                Event::ControllerButtonDown { which, button, .. } => {
+                    if let Some(k) = gamepads
+                        .as_mut()
+                        .and_then(|pads| pads.press(which, button, true))
+                    {
+                        press_key(&mut chip8, &mut recording, &playing, k, true);
+                    }
+                }
+                Event::ControllerButtonUp { which, button, .. } => {
+                    if let Some(k) = gamepads
+                        .as_mut()
+                        .and_then(|pads| pads.press(which, button, false))
+                    {
+                        press_key(&mut chip8, &mut recording, &playing, k, false);
                     }
                 }
 
@@ -90,72 +374,233 @@ fn main() {
             }
         }
 
+        // While BACKSPACE is held, go back one snapshot per frame instead of running the game
+        let rewinding = event_pump
+            .keyboard_state()
+            .is_scancode_pressed(Scancode::Backspace);
+        if rewinding && recording.is_none() && playing.is_none() {
+            if let Err(err) = rewind.step_back(&mut chip8) {
+                eprintln!("Unable to rewind: {}", err);
+            }
+            draw_screen(&chip8, &mut canvas, &palette);
+            continue;
+        }
+
+        // Nothing runs while the game is paused, not even the movie
+        if paused {
+            pause_sound(&audio);
+            draw_screen(&chip8, &mut canvas, &palette);
+            continue;
+        }
+
+        // Send the key presses of the movie, and give the control back once it is over
+        if let Some(movie) = &playing {
+            if !movie.play_frame(frame, &mut chip8) {
+                println!("The movie is over");
+                playing = None;
+            }
+        }
+
         // Execute a clock-cycle
-        for _ in 0..TICKS_PER_FRAME {
-            chip8.tick();
+        // If the game runs into an invalid instruction, report it and stop the emulation
+        tick_budget += ips as u64;
+        let ticks = tick_budget / FRAMES_PER_SECOND;
+        tick_budget %= FRAMES_PER_SECOND;
+        for _ in 0..ticks {
+            if let Some(debugger) = &mut debugger {
+                if let Some(reason) = debugger.check(&mut chip8) {
+                    pause_sound(&audio);
+                    println!("{}", reason);
+                    if !debug_prompt(&mut chip8, debugger, &mut canvas, &palette) {
+                        break 'gameloop;
+                    }
+                }
+            }
+            if let Err(err) = chip8.tick() {
+                eprintln!("Emulation stopped: {}", err);
+                break 'gameloop;
+            }
         }
+        // The buzzer sounds for the whole frame in which the sound timer is active
+        update_sound(&mut audio, &chip8);
         chip8.tick_timers();
+        rewind.record(&chip8);
+        if let Some(movie) = &mut recording {
+            movie.end_frame();
+        }
+        frame += 1;
 
         // Draw the screen
-        draw_screen(&chip8, &mut canvas);
+        draw_screen(&chip8, &mut canvas, &palette);
+
+        // The game has exited on its own
+        if chip8.is_halted() {
+            break 'gameloop;
+        }
+    }
+
+    // Save the recorded session once the game is over
+    if let (Some(path), Some(movie)) = (record_path, recording) {
+        match fs::write(&path, movie.to_bytes()) {
+            Ok(()) => println!("Recorded {} frames to {}", movie.frames(), path),
+            Err(err) => eprintln!("Unable to save the movie: {}", err),
+        }
     }
 }
 
-// Draw's the canvas
-fn draw_screen(chip8: &CPU, canvas: &mut Canvas<Window>) {
-    // Clear canvas as black
-    canvas.set_draw_color(Color::RGB(0, 0, 0));
+// Report an error in the options, the config or the files given, before the game starts
+fn fail(message: &str) -> ! {
+    eprintln!("{}", message);
+    process::exit(2);
+}
+
+// Send a key press to the CPU, and record it when a movie is being recorded
+// The keyboard is ignored while a movie is played back
+fn press_key(
+    chip8: &mut CPU,
+    recording: &mut Option<Movie>,
+    playing: &Option<Movie>,
+    key: usize,
+    pressed: bool,
+) {
+    if playing.is_some() {
+        return;
+    }
+    chip8.keypress(key, pressed);
+    if let Some(movie) = recording {
+        movie.record(key, pressed);
+    }
+}
+
+// Read and run debugger commands from the terminal, redrawing the screen after each of them
+// Returns false when the debugger is quit, true when the game should resume
+fn debug_prompt(
+    chip8: &mut CPU,
+    debugger: &mut Debugger,
+    canvas: &mut Canvas<Window>,
+    palette: &[Color; 4],
+) -> bool {
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+    loop {
+        print!("(chip8) ");
+        let _ = io::stdout().flush();
+        let line = match lines.next() {
+            Some(Ok(line)) => line,
+            _ => return false,
+        };
+
+        let (action, output) = debugger.command(chip8, &line);
+        if !output.is_empty() {
+            println!("{}", output);
+        }
+        draw_screen(chip8, canvas, palette);
+        match action {
+            Action::Prompt => (),
+            Action::Resume => return true,
+            Action::Quit => return false,
+        }
+    }
+}
+
+// Send the sound state of the CPU to the audio thread
+fn update_sound(audio: &mut Option<AudioDevice<Buzzer>>, chip8: &CPU) {
+    if let Some(device) = audio {
+        device.lock().0.update(chip8);
+        device.resume();
+    }
+}
+
+// Silence the buzzer while the game is paused
+fn pause_sound(audio: &Option<AudioDevice<Buzzer>>) {
+    if let Some(device) = audio {
+        device.pause();
+    }
+}
+
+// Draw's the canvas, with the colors indexed by the XO-CHIP planes the pixels are set on
+fn draw_screen(chip8: &CPU, canvas: &mut Canvas<Window>, palette: &[Color; 4]) {
+    // Clear canvas with the background color
+    canvas.set_draw_color(palette[0]);
     canvas.clear();
 
-    let screen_buf = chip8.get_display();
-    // Now set draw color to white, iterate through each point and see if it should be drawn
-    canvas.set_draw_color(Color::RGB(255, 255, 255));
-    for (i, pixel) in screen_buf.iter().enumerate() {
-        if *pixel {
+    let display = chip8.get_display();
+    // Scale the pixels so that the screen fills the window, whatever resolution it is in, and center it
+    // The screen is only smaller than the window in fullscreen, when the aspect ratios differ
+    let (width, height) = canvas.output_size().unwrap();
+    let scale = (width / display.width as u32)
+        .min(height / display.height as u32)
+        .max(1);
+    let left = (width as i32 - (display.width as u32 * scale) as i32) / 2;
+    let top = (height as i32 - (display.height as u32 * scale) as i32) / 2;
+    // Now iterate through each point and see if it should be drawn, and with which color
+    for (i, &pixel) in display.pixels.iter().enumerate() {
+        if pixel != 0 {
+            canvas.set_draw_color(palette[pixel as usize]);
+
             // Convert our 1D array's index into a 2D (x,y) position
-            let x = (i % SCREEN_WIDTH) as u32;
-            let y = (i / SCREEN_WIDTH) as u32;
+            let x = (i % display.width) as u32;
+            let y = (i / display.width) as u32;
 
-            // Draw a rectangle at (x,y), scaled up by our SCALE value
-            let rect = Rect::new((x * SCALE) as i32, (y * SCALE) as i32, SCALE, SCALE);
+            // Draw a rectangle at (x,y), scaled up by our scale value
+            let rect = Rect::new(
+                left + (x * scale) as i32,
+                top + (y * scale) as i32,
+                scale,
+                scale,
+            );
             canvas.fill_rect(rect).unwrap();
         }
     }
     canvas.present();
 }
 
-/*
-    Converts a keypress on the actual device to a byte KEY for the emulator
-
-    Keyboard                    Chip-8
-    +---+---+---+---+           +---+---+---+---+
-    | 1 | 2 | 3 | 4 |           | 1 | 2 | 3 | C |
-    +---+---+---+---+           +---+---+---+---+
-    | Q | W | E | R |           | 4 | 5 | 6 | D |
-    +---+---+---+---+     =>    +---+---+---+---+
-    | A | S | D | F |           | 7 | 8 | 9 | E |
-    +---+---+---+---+           +---+---+---+---+
-    | Z | X | C | V |           | A | 0 | B | F |
-    +---+---+---+---+           +---+---+---+---+
-*/
-fn key2btn(key: Keycode) -> Option<usize> {
-    match key {
-        Keycode::Num1 => Some(0x1),
-        Keycode::Num2 => Some(0x2),
-        Keycode::Num3 => Some(0x3),
-        Keycode::Num4 => Some(0xC),
-        Keycode::Q => Some(0x4),
-        Keycode::W => Some(0x5),
-        Keycode::E => Some(0x6),
-        Keycode::R => Some(0xD),
-        Keycode::A => Some(0x7),
-        Keycode::S => Some(0x8),
-        Keycode::D => Some(0x9),
-        Keycode::F => Some(0xE),
-        Keycode::Z => Some(0xA),
-        Keycode::X => Some(0x0),
-        Keycode::C => Some(0xB),
-        Keycode::V => Some(0xF),
-        _ => None,
-    }
+// The name of the key at the position of the scancode, as used by the keymaps
+// Only the keys which can be bound are named, not the function keys used by the emulator
+fn key_code(scancode: Scancode) -> Option<String> {
+    let code = match scancode {
+        Scancode::Up => "ArrowUp",
+        Scancode::Down => "ArrowDown",
+        Scancode::Left => "ArrowLeft",
+        Scancode::Right => "ArrowRight",
+        Scancode::Space => "Space",
+        Scancode::Return => "Enter",
+        Scancode::Tab => "Tab",
+        Scancode::LShift => "ShiftLeft",
+        Scancode::RShift => "ShiftRight",
+        Scancode::LCtrl => "ControlLeft",
+        Scancode::RCtrl => "ControlRight",
+        Scancode::LAlt => "AltLeft",
+        Scancode::RAlt => "AltRight",
+        Scancode::Minus => "Minus",
+        Scancode::Equals => "Equal",
+        Scancode::LeftBracket => "BracketLeft",
+        Scancode::RightBracket => "BracketRight",
+        Scancode::Backslash => "Backslash",
+        Scancode::Semicolon => "Semicolon",
+        Scancode::Apostrophe => "Quote",
+        Scancode::Grave => "Backquote",
+        Scancode::Comma => "Comma",
+        Scancode::Period => "Period",
+        Scancode::Slash => "Slash",
+        Scancode::KpDivide => "NumpadDivide",
+        Scancode::KpMultiply => "NumpadMultiply",
+        Scancode::KpMinus => "NumpadSubtract",
+        Scancode::KpPlus => "NumpadAdd",
+        Scancode::KpEnter => "NumpadEnter",
+        Scancode::KpPeriod => "NumpadDecimal",
+        // The letters and digits are named after the QWERTY keys at their position
+        _ => {
+            let name = scancode.name();
+            return match name.as_bytes() {
+                [b'A'..=b'Z'] => Some(format!("Key{}", name)),
+                [b'0'..=b'9'] => Some(format!("Digit{}", name)),
+                _ => name.strip_prefix("Keypad ").and_then(|digit| {
+                    let is_digit = digit.len() == 1 && digit.as_bytes()[0].is_ascii_digit();
+                    is_digit.then(|| format!("Numpad{}", digit))
+                }),
+            };
+        }
+    };
+    Some(code.to_string())
 }
@@ -0,0 +1,148 @@
+use chip8::config::{parse_color, Settings};
+use chip8::{Keymap, Platform, Quirks};
+use std::fs;
+
+pub const USAGE: &str = "Usage: cargo run path/to/game [chip8|schip|xochip] [options]
+
+Options:
+    --platform NAME    chip8, schip or xochip (default chip8)
+    --quirks NAME      vip, chip48, schip, xochip or modern (default: the usual ones of the platform)
+    --ips N            Instructions run per second (default 600)
+    --scale N          Size of the lo-res pixels on the screen, in pixels (default 16)
+    --fullscreen       Fill the whole screen
+    --fg RRGGBB        Color of the pixels which are on (default FFFFFF)
+    --bg RRGGBB        Color of the pixels which are off (default 000000)
+    --seed N           Seed of the random number generator (default: random)
+    --paused           Start paused, until F2 is pressed
+    --keymap NAME      Keys of the CHIP-8 keypad: keypad (1234, QWER, ASDF and ZXCV, by default) or numpad,
+                       or the path to a file binding more keys on top of them
+    --volume N         Volume of the buzzer, from 0 to 100 (default 25)
+    --pitch HZ         Pitch of the buzzer (default 440)
+    --record PATH      Record the session into a movie
+    --play PATH        Play back a movie recorded with --record
+    --config PATH      Settings of the ROMs (default: chip8.toml next to the ROM)
+    --save-settings    Save the settings given on the command line into the config file
+    --help             Print this help
+
+The platform, quirks, speed, scale, colors, volume, pitch and keys of every ROM can be kept in the config file with
+--save-settings, so that they don't need to be given again the next time it is played
+
+The keys follow their position on the keyboard, whatever its layout, and are named as in the browsers, such as `KeyW`,
+`Digit1`, `ArrowUp` or `Space`
+The keymap file binds one key per line to a CHIP-8 key, such as `ArrowUp = 5` or `Space = 0xA`";
+
+pub const DEFAULT_IPS: u32 = 600;
+pub const DEFAULT_SCALE: u32 = 16;
+pub const DEFAULT_FOREGROUND: [u8; 3] = [255, 255, 255];
+pub const DEFAULT_BACKGROUND: [u8; 3] = [0, 0, 0];
+pub const DEFAULT_VOLUME: u32 = 25;
+pub const DEFAULT_PITCH: f32 = 440.0;
+
+// Everything that can be set from the command line
+// The settings which are given override the ones of the config file, and are saved into it with --save-settings
+pub struct Options {
+    pub rom_path: String,
+    pub settings: Settings,
+    pub save_settings: bool,
+    pub fullscreen: bool,
+    pub seed: Option<u64>,
+    pub paused: bool,
+    pub config_path: Option<String>,
+    pub record_path: Option<String>,
+    pub play_path: Option<String>,
+}
+
+impl Options {
+    // Parse the arguments, without the name of the program
+    pub fn parse(args: impl Iterator<Item = String>) -> Result<Options, String> {
+        let mut args = args;
+        let mut positional = Vec::new();
+        let mut options = Options {
+            rom_path: String::new(),
+            settings: Settings::default(),
+            save_settings: false,
+            fullscreen: false,
+            seed: None,
+            paused: false,
+            config_path: None,
+            record_path: None,
+            play_path: None,
+        };
+        let settings = &mut options.settings;
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or(format!("Missing value for {}", arg));
+            match arg.as_str() {
+                "--platform" => settings.platform = Some(name::<Platform>(value()?)?),
+                "--quirks" => settings.quirks = Some(name::<Quirks>(value()?)?),
+                "--ips" => settings.ips = Some(number(&arg, &value()?, 1, u32::MAX)?),
+                "--scale" => settings.scale = Some(number(&arg, &value()?, 1, 64)?),
+                "--fullscreen" => options.fullscreen = true,
+                "--fg" => settings.foreground = Some(color(value()?)?),
+                "--bg" => settings.background = Some(color(value()?)?),
+                "--seed" => options.seed = Some(number(&arg, &value()?, 0, u64::MAX)?),
+                "--paused" => options.paused = true,
+                "--keymap" => {
+                    let keymap = value()?;
+                    match keymap.parse::<Keymap>() {
+                        Ok(_) => settings.keymap = Some(keymap),
+                        Err(_) => settings.keys = Some(read_keymap(&keymap)?),
+                    }
+                }
+                "--volume" => settings.volume = Some(number(&arg, &value()?, 0, 100)?),
+                "--pitch" => settings.pitch = Some(number(&arg, &value()?, 1.0, 20000.0)?),
+                "--config" => options.config_path = Some(value()?),
+                "--save-settings" => options.save_settings = true,
+                "--record" => options.record_path = Some(value()?),
+                "--play" => options.play_path = Some(value()?),
+                _ if arg.starts_with("--") => return Err(format!("Unknown option {}", arg)),
+                _ => positional.push(arg),
+            }
+        }
+
+        // The platform can also be given after the ROM, as it used to be
+        options.rom_path = match positional.as_slice() {
+            [rom_path] => rom_path.clone(),
+            [rom_path, platform] if options.settings.platform.is_none() => {
+                options.settings.platform = Some(name::<Platform>(platform.clone())?);
+                rom_path.clone()
+            }
+            _ => return Err(USAGE.to_string()),
+        };
+        if options.record_path.is_some() && options.play_path.is_some() {
+            return Err("--record and --play can't be used together".to_string());
+        }
+        Ok(options)
+    }
+}
+
+fn number<T: std::str::FromStr + PartialOrd>(
+    name: &str,
+    text: &str,
+    min: T,
+    max: T,
+) -> Result<T, String> {
+    text.parse()
+        .ok()
+        .filter(|value| *value >= min && *value <= max)
+        .ok_or(format!("Invalid value for {}: {}", name, text))
+}
+
+// The name of a platform or quirks preset, once checked
+fn name<T: std::str::FromStr<Err = String>>(text: String) -> Result<String, String> {
+    text.parse::<T>()?;
+    Ok(text)
+}
+
+// A color such as `FF8800` or `#FF8800`, once checked
+fn color(text: String) -> Result<String, String> {
+    parse_color(&text)?;
+    Ok(text.trim_start_matches('#').to_uppercase())
+}
+
+// Read a keymap file, as described in the usage
+fn read_keymap(path: &str) -> Result<Keymap, String> {
+    fs::read_to_string(path)
+        .map_err(|err| err.to_string())
+        .and_then(|text| Keymap::parse(&text))
+        .map_err(|err| format!("Unable to read the keymap {}: {}", path, err))
+}
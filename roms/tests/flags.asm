@@ -0,0 +1,158 @@
+; Checks the flag set in VF by the arithmetic instructions, and that the flag wins when VF is the destination
+;
+; Each check draws a mark, ten to a row from the top left corner, in this order:
+;     8XY4 (result) 8XY4 (no carry) 8XY4 (carry) 8XY4 (VF as X) 8XY4 (VF as Y: result) 8XY4 (VF as Y: no carry)
+;     8XY5 (no borrow) 8XY5 (borrow) 8XY5 (equal) 8XY5 (VF as X)
+;     8XY7 (result) 8XY7 (no borrow) 8XY7 (borrow) 8XY7 (VF as X)
+;     8XY6 (bit out) 8XY6 (no bit out) 8XYE (bit out) 8XYE (no bit out) 8XY6 (VF as X)
+; A tick is a pass, a cross a failure, and a missing mark a check which never ran
+        CLS
+        LD VC, 2
+        LD VD, 2
+
+        ; 8XY4
+        LD V1, 0x10
+        LD V2, 0x20
+        ADD V1, V2
+        LD V4, VF
+        LD V0, 0
+        SNE V1, 0x30
+        LD V0, 1
+        CALL mark
+        LD V0, 0
+        SNE V4, 0
+        LD V0, 1
+        CALL mark
+        LD V1, 0xF0
+        ADD V1, V2
+        LD V0, 0
+        SNE VF, 1
+        LD V0, 1
+        CALL mark
+        LD VF, 0xF0
+        ADD VF, V2
+        LD V0, 0
+        SNE VF, 1
+        LD V0, 1
+        CALL mark
+        ; The value of VF is read before the flag is written
+        LD VF, 0x20
+        LD V1, 0x10
+        ADD V1, VF
+        LD V4, VF
+        LD V0, 0
+        SNE V1, 0x30
+        LD V0, 1
+        CALL mark
+        LD V0, 0
+        SNE V4, 0
+        LD V0, 1
+        CALL mark
+
+        ; 8XY5
+        LD V1, 0x30
+        LD V2, 0x10
+        SUB V1, V2
+        LD V0, 0
+        SNE VF, 1
+        LD V0, 1
+        CALL mark
+        LD V1, 0x10
+        LD V2, 0x30
+        SUB V1, V2
+        LD V0, 0
+        SNE VF, 0
+        LD V0, 1
+        CALL mark
+        LD V1, 0x30
+        SUB V1, V2
+        LD V0, 0
+        SNE VF, 1
+        LD V0, 1
+        CALL mark
+        LD VF, 0x30
+        LD V2, 0x10
+        SUB VF, V2
+        LD V0, 0
+        SNE VF, 1
+        LD V0, 1
+        CALL mark
+
+        ; 8XY7
+        LD V1, 0x10
+        LD V2, 0x30
+        SUBN V1, V2
+        LD V4, VF
+        LD V0, 0
+        SNE V1, 0x20
+        LD V0, 1
+        CALL mark
+        LD V0, 0
+        SNE V4, 1
+        LD V0, 1
+        CALL mark
+        LD V1, 0x30
+        LD V2, 0x10
+        SUBN V1, V2
+        LD V0, 0
+        SNE VF, 0
+        LD V0, 1
+        CALL mark
+        LD VF, 0x10
+        LD V2, 0x30
+        SUBN VF, V2
+        LD V0, 0
+        SNE VF, 1
+        LD V0, 1
+        CALL mark
+
+        ; 8XY6 and 8XYE, with Y being X so that the shifting quirk doesn't matter
+        LD V1, 0x81
+        SHR V1, V1
+        LD V0, 0
+        SNE VF, 1
+        LD V0, 1
+        CALL mark
+        LD V1, 0x80
+        SHR V1, V1
+        LD V0, 0
+        SNE VF, 0
+        LD V0, 1
+        CALL mark
+        LD V1, 0x81
+        SHL V1, V1
+        LD V0, 0
+        SNE VF, 1
+        LD V0, 1
+        CALL mark
+        LD V1, 0x01
+        SHL V1, V1
+        LD V0, 0
+        SNE VF, 0
+        LD V0, 1
+        CALL mark
+        LD VF, 0x81
+        SHR VF, VF
+        LD V0, 0
+        SNE VF, 1
+        LD V0, 1
+        CALL mark
+
+done:   JP done
+
+; Draw the result of a check, a tick if V0 is 1 and a cross otherwise, at (VC, VD), then move on to the next cell
+; VF and I are clobbered
+mark:   LD I, cross
+        SE V0, 1
+        JP put
+        LD I, tick
+put:    DRW VC, VD, 5
+        ADD VC, 6
+        SE VC, 62
+        RET
+        LD VC, 2
+        ADD VD, 6
+        RET
+
+tick:   DB 0b00001000, 0b00010000, 0b10100000, 0b01000000, 0b00000000
+cross:  DB 0b10001000, 0b01010000, 0b00100000, 0b01010000, 0b10001000
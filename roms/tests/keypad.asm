@@ -0,0 +1,63 @@
+; Checks the keypad instructions, with key 5 pressed then released, and key A pressed
+;
+; The marks are drawn from the top left corner, in this order:
+;     EXA1 (no key pressed) FX0A (key 5) EX9E (key 5 held) EXA1 (key 5 released) FX0A (key A)
+; A tick is a pass, a cross a failure, and a missing mark a check which never ran
+        CLS
+        LD VC, 2
+        LD VD, 2
+
+        ; EXA1 skips when the key isn't pressed
+        LD V1, 5
+        LD V2, 0
+        SKNP V1
+        LD V2, 1
+        LD V0, 0
+        SNE V2, 0
+        LD V0, 1
+        CALL mark
+
+        ; FX0A waits for a key and returns it
+        LD V2, K
+        LD V0, 0
+        SNE V2, 5
+        LD V0, 1
+        CALL mark
+        ; EX9E skips while the key is held
+        LD V3, 0
+        SKP V2
+        LD V3, 1
+        LD V0, 0
+        SNE V3, 0
+        LD V0, 1
+        CALL mark
+        ; Once the key is released, EXA1 skips the jump
+release: SKNP V2
+        JP release
+        LD V0, 1
+        CALL mark
+
+        LD V2, K
+        LD V0, 0
+        SNE V2, 0xA
+        LD V0, 1
+        CALL mark
+
+done:   JP done
+
+; Draw the result of a check, a tick if V0 is 1 and a cross otherwise, at (VC, VD), then move on to the next cell
+; VF and I are clobbered
+mark:   LD I, cross
+        SE V0, 1
+        JP put
+        LD I, tick
+put:    DRW VC, VD, 5
+        ADD VC, 6
+        SE VC, 62
+        RET
+        LD VC, 2
+        ADD VD, 6
+        RET
+
+tick:   DB 0b00001000, 0b00010000, 0b10100000, 0b01000000, 0b00000000
+cross:  DB 0b10001000, 0b01010000, 0b00100000, 0b01010000, 0b10001000
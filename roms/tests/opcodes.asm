@@ -0,0 +1,256 @@
+; Checks the result of every CHIP-8 instruction whose behaviour doesn't depend on the quirks
+;
+; Each check draws a mark, ten to a row from the top left corner, in this order:
+;     3XNN 4XNN 5XY0 9XY0 7XNN (result) 7XNN (VF untouched) 8XY0 8XY1 8XY2 8XY3
+;     8XY4 8XY5 8XY7 8XY6 8XYE ANNN+FX1E+FX65 FX55 (two registers) FX33 (first two digits)
+;     FX33 (last digit) 2NNN+00EE 1NNN FX29 FX15+FX07 CXNN DXYN
+; A tick is a pass, a cross a failure, and a missing mark a check which never ran
+        CLS
+        LD VC, 2
+        LD VD, 2
+
+        ; 3XNN skips when equal
+        LD V1, 5
+        LD V2, 0
+        SE V1, 5
+        LD V2, 1
+        LD V0, 0
+        SNE V2, 0
+        LD V0, 1
+        CALL mark
+        ; 4XNN skips when not equal
+        LD V2, 0
+        SNE V1, 6
+        LD V2, 1
+        LD V0, 0
+        SNE V2, 0
+        LD V0, 1
+        CALL mark
+        ; 5XY0 skips when equal
+        LD V3, 5
+        LD V2, 0
+        SE V1, V3
+        LD V2, 1
+        LD V0, 0
+        SNE V2, 0
+        LD V0, 1
+        CALL mark
+        ; 9XY0 skips when not equal
+        LD V3, 6
+        LD V2, 0
+        SNE V1, V3
+        LD V2, 1
+        LD V0, 0
+        SNE V2, 0
+        LD V0, 1
+        CALL mark
+
+        ; 7XNN wraps around, and leaves VF untouched
+        LD VF, 7
+        LD V1, 0xFF
+        ADD V1, 2
+        LD V4, VF
+        LD V0, 0
+        SNE V1, 1
+        LD V0, 1
+        CALL mark
+        LD V0, 0
+        SNE V4, 7
+        LD V0, 1
+        CALL mark
+        ; 8XY0
+        LD V2, V1
+        LD V0, 0
+        SNE V2, 1
+        LD V0, 1
+        CALL mark
+        ; 8XY1
+        LD V1, 0x0F
+        LD V2, 0xF0
+        OR V1, V2
+        LD V0, 0
+        SNE V1, 0xFF
+        LD V0, 1
+        CALL mark
+        ; 8XY2
+        LD V1, 0x3C
+        LD V2, 0x0F
+        AND V1, V2
+        LD V0, 0
+        SNE V1, 0x0C
+        LD V0, 1
+        CALL mark
+        ; 8XY3
+        LD V1, 0x3C
+        XOR V1, V2
+        LD V0, 0
+        SNE V1, 0x33
+        LD V0, 1
+        CALL mark
+
+        ; 8XY4 wraps around
+        LD V1, 0x80
+        LD V2, 0x90
+        ADD V1, V2
+        LD V0, 0
+        SNE V1, 0x10
+        LD V0, 1
+        CALL mark
+        ; 8XY5 wraps around
+        LD V1, 0x10
+        LD V2, 0x20
+        SUB V1, V2
+        LD V0, 0
+        SNE V1, 0xF0
+        LD V0, 1
+        CALL mark
+        ; 8XY7
+        LD V1, 0x10
+        LD V2, 0x30
+        SUBN V1, V2
+        LD V0, 0
+        SNE V1, 0x20
+        LD V0, 1
+        CALL mark
+        ; 8XY6 and 8XYE shift VX in place whatever the shifting quirk, when Y is X
+        LD V1, 0x81
+        SHR V1, V1
+        LD V0, 0
+        SNE V1, 0x40
+        LD V0, 1
+        CALL mark
+        LD V1, 0x81
+        SHL V1, V1
+        LD V0, 0
+        SNE V1, 0x02
+        LD V0, 1
+        CALL mark
+        ; ANNN, FX1E and FX65
+        LD I, data
+        LD V1, 2
+        ADD I, V1
+        LD V0, [I]
+        LD V1, V0
+        LD V0, 0
+        SNE V1, 3
+        LD V0, 1
+        CALL mark
+        ; FX55, read back with FX65
+        LD V0, 0x12
+        LD V1, 0x34
+        LD V2, 0x56
+        LD I, scratch
+        LD [I], V2
+        LD V1, 0
+        LD V2, 0
+        LD I, scratch
+        LD V2, [I]
+        LD V0, 0
+        SNE V1, 0x34
+        LD V0, 1
+        CALL mark
+        LD V0, 0
+        SNE V2, 0x56
+        LD V0, 1
+        CALL mark
+        ; FX33
+        LD V1, 137
+        LD I, scratch
+        LD B, V1
+        LD V2, [I]
+        LD V4, V0
+        LD V0, 0
+        SNE V4, 1
+        LD V0, 1
+        CALL mark
+        LD V0, 0
+        SNE V1, 3
+        LD V0, 1
+        CALL mark
+        LD V0, 0
+        SNE V2, 7
+        LD V0, 1
+        CALL mark
+
+        ; 2NNN and 00EE
+        LD V1, 0
+        CALL set_v1
+        LD V0, 0
+        SNE V1, 0x42
+        LD V0, 1
+        CALL mark
+        ; 1NNN
+        LD V1, 1
+        JP jumped
+        LD V1, 0
+jumped: LD V0, 0
+        SNE V1, 1
+        LD V0, 1
+        CALL mark
+        ; FX29 points at the font, whose A starts with 0xF0
+        LD V1, 0xA
+        LD F, V1
+        LD V0, [I]
+        LD V1, V0
+        LD V0, 0
+        SNE V1, 0xF0
+        LD V0, 1
+        CALL mark
+        ; FX15 and FX07: the delay timer counts down from the value it was set to
+        LD V1, 10
+        LD DT, V1
+        LD V2, DT
+        LD V0, 0
+        SNE V2, 10
+        LD V0, 1
+        SNE V2, 9
+        LD V0, 1
+        CALL mark
+        ; CXNN masks the random number
+        RND V1, 0x00
+        RND V2, 0x0F
+        LD V3, 0xF0
+        AND V2, V3
+        OR V1, V2
+        LD V0, 0
+        SNE V1, 0
+        LD V0, 1
+        CALL mark
+        ; DXYN sets VF on collision, and XORs the pixels
+        LD V1, 56
+        LD V2, 26
+        LD V3, 0
+        LD F, V3
+        DRW V1, V2, 5
+        LD V4, VF
+        DRW V1, V2, 5
+        LD V0, 0
+        SNE V4, 0
+        LD V0, 1
+        SNE VF, 0
+        LD V0, 0
+        CALL mark
+
+done:   JP done
+
+set_v1: LD V1, 0x42
+        RET
+
+; Draw the result of a check, a tick if V0 is 1 and a cross otherwise, at (VC, VD), then move on to the next cell
+; VF and I are clobbered
+mark:   LD I, cross
+        SE V0, 1
+        JP put
+        LD I, tick
+put:    DRW VC, VD, 5
+        ADD VC, 6
+        SE VC, 62
+        RET
+        LD VC, 2
+        ADD VD, 6
+        RET
+
+tick:   DB 0b00001000, 0b00010000, 0b10100000, 0b01000000, 0b00000000
+cross:  DB 0b10001000, 0b01010000, 0b00100000, 0b01010000, 0b10001000
+data:   DB 1, 2, 3, 4
+scratch: DB 0, 0, 0
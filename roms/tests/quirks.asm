@@ -0,0 +1,119 @@
+; Checks which behaviour the ambiguous instructions have, one mark for each quirk
+;
+; The marks are drawn from the top left corner, in this order:
+;     jumping (BNNN jumps to VX + NN) shifting (8XY6 shifts VX in place) VF reset (8XY1, 8XY2 and 8XY3 reset VF)
+;     memory (FX55 increments I) display wait (DXYN waits for the next frame) clipping (DXYN clips the sprites)
+; A tick means the quirk is on, a cross that it's off, and a missing mark a check which never ran
+; The display wait check expects the program to run more than 8 instructions a frame
+        CLS
+        LD VC, 2
+        LD VD, 2
+
+        ; Jumping, with a table that has to stay in the first 256 bytes of the program
+        LD V0, 0
+        LD V2, 2
+        JP V0, table
+table:  JP jump_off
+        JP jump_on
+jump_on: LD V0, 1
+        JP jumped
+jump_off: LD V0, 0
+jumped: CALL mark
+
+        ; Shifting
+        LD V1, 0x10
+        LD V2, 0x04
+        SHR V1, V2
+        LD V0, 0
+        SNE V1, 0x08
+        LD V0, 1
+        CALL mark
+
+        ; VF reset, which has to happen for the three instructions
+        LD V1, 1
+        LD V2, 2
+        LD V3, 0
+        LD VF, 5
+        OR V1, V2
+        SE VF, 0
+        LD V3, 1
+        LD VF, 5
+        AND V1, V2
+        SE VF, 0
+        LD V3, 1
+        LD VF, 5
+        XOR V1, V2
+        SE VF, 0
+        LD V3, 1
+        LD V0, 0
+        SNE V3, 0
+        LD V0, 1
+        CALL mark
+
+        ; Memory: FX65 reads the byte after the one FX55 wrote if I was incremented
+        LD V0, 0xAA
+        LD I, scratch
+        LD [I], V0
+        LD V0, [I]
+        LD V1, V0
+        LD V0, 0
+        SNE V1, 0x55
+        LD V0, 1
+        CALL mark
+
+        ; Display wait: right after the delay timer goes down, two sprites are drawn in the same frame only if DXYN
+        ; doesn't wait
+        LD V2, 0
+        LD V3, 30
+        LD V1, 3
+        LD DT, V1
+sync:   LD V1, DT
+        SE V1, 2
+        JP sync
+        LD I, dot
+        DRW V2, V3, 1
+        DRW V2, V3, 1
+        LD V1, DT
+        LD V0, 0
+        SNE V1, 1
+        LD V0, 1
+        CALL mark
+
+        ; Clipping: a line drawn across the right edge doesn't reach the dot drawn on the left edge
+        LD V1, 60
+        LD V2, 0
+        LD V3, 31
+        LD I, line
+        DRW V1, V3, 1
+        LD I, dot
+        DRW V2, V3, 1
+        LD V4, VF
+        DRW V2, V3, 1
+        LD I, line
+        DRW V1, V3, 1
+        LD V0, 0
+        SNE V4, 0
+        LD V0, 1
+        CALL mark
+
+done:   JP done
+
+; Draw the result of a check, a tick if V0 is 1 and a cross otherwise, at (VC, VD), then move on to the next cell
+; VF and I are clobbered
+mark:   LD I, cross
+        SE V0, 1
+        JP put
+        LD I, tick
+put:    DRW VC, VD, 5
+        ADD VC, 6
+        SE VC, 62
+        RET
+        LD VC, 2
+        ADD VD, 6
+        RET
+
+tick:   DB 0b00001000, 0b00010000, 0b10100000, 0b01000000, 0b00000000
+cross:  DB 0b10001000, 0b01010000, 0b00100000, 0b01010000, 0b10001000
+dot:    DB 0b10000000
+line:   DB 0b11111111
+scratch: DB 0, 0x55
@@ -1,17 +1,102 @@
+use chip8::config::{self, Config, Settings};
+use chip8::database::{self, RomInfo};
 use chip8::*;
-use wasm_bindgen::prelude::*;
 use js_sys::Uint8Array;
+use wasm_bindgen::prelude::*;
 use wasm_bindgen::JsCast;
-use web_sys::{CanvasRenderingContext2d, HtmlCanvasElement, KeyboardEvent};
+use web_sys::{AudioContext, CanvasRenderingContext2d, HtmlCanvasElement, KeyboardEvent};
+
+// The buzzer is a square wave, whose volume (from 0 to 1) and pitch (in Hz) can be changed from JavaScript
+const DEFAULT_VOLUME: f32 = 0.25;
+const DEFAULT_PITCH: f32 = 440.0;
+const DEFAULT_IPS: u32 = 600;
+const DEFAULT_FOREGROUND: &str = "#FFFFFF";
+const DEFAULT_BACKGROUND: &str = "#000000";
+// The sound is rendered one frame at a time, and queued a little ahead so that the next frames can follow on
+const FRAMES_PER_SECOND: f32 = 60.0;
+const AUDIO_LATENCY: f64 = 0.05;
 
 #[wasm_bindgen]
 pub struct CPUWasm {
     chip8: CPU,
     ctx: CanvasRenderingContext2d,
+    beeper: Option<Beeper>,
+    volume: f32,
+    pitch: f32,
+    config: Config,
+    rom_hash: Option<[u8; 20]>, // Set once a game is loaded, so that the changes of settings are saved for it
+    settings: Settings,         // Settings of the loaded game
+    info: Option<RomInfo>,      // What the ROM database knows about the loaded game
+    keymap: Keymap,
+}
+
+// The sound of the buzzer, rendered by the emulator and queued into the audio context frame by frame
+struct Beeper {
+    ctx: AudioContext,
+    audio: Audio,
+    samples: Vec<f32>,
+    next_time: f64, // Time at which the next frame starts playing, in the clock of the audio context
+}
+
+impl Beeper {
+    fn new() -> Result<Beeper, JsValue> {
+        let ctx = AudioContext::new()?;
+        let sample_rate = ctx.sample_rate();
+        let samples = vec![0.0; (sample_rate / FRAMES_PER_SECOND) as usize];
+        Ok(Beeper {
+            ctx,
+            audio: Audio::new(sample_rate as u32),
+            samples,
+            next_time: 0.0,
+        })
+    }
+
+    // Queue the sound of a frame after the previous one, or a little ahead of now if the previous one is over
+    fn play_frame(&mut self) -> Result<(), JsValue> {
+        self.audio.fill(&mut self.samples);
+        let sample_rate = self.audio.sample_rate() as f32;
+        let buffer = self
+            .ctx
+            .create_buffer(1, self.samples.len() as u32, sample_rate)?;
+        buffer.copy_to_channel(&self.samples, 0)?;
+        let source = self.ctx.create_buffer_source()?;
+        source.set_buffer(Some(&buffer));
+        source.connect_with_audio_node(&self.ctx.destination())?;
+
+        let now = self.ctx.current_time();
+        if self.next_time < now {
+            self.next_time = now + AUDIO_LATENCY;
+        }
+        source.start_with_when(self.next_time)?;
+        self.next_time += self.samples.len() as f64 / sample_rate as f64;
+        Ok(())
+    }
 }
 
 #[wasm_bindgen]
 impl CPUWasm {
+    fn apply_volume(&mut self, volume: f32) {
+        self.volume = volume;
+        if let Some(beeper) = &mut self.beeper {
+            beeper.audio.set_volume(volume);
+        }
+    }
+
+    fn apply_pitch(&mut self, pitch: f32) {
+        self.pitch = pitch;
+        if let Some(beeper) = &mut self.beeper {
+            beeper.audio.set_tone(pitch);
+        }
+    }
+
+    // Save the changed settings for the loaded game, if there is one
+    fn remember(&mut self, changes: Settings) {
+        if let Some(rom_hash) = &self.rom_hash {
+            self.config.update(rom_hash, &changes);
+            self.settings = self.settings.merge(&changes);
+        }
+    }
+
     #[wasm_bindgen(constructor)]
     pub fn new() -> Result<CPUWasm, JsValue> {
         let chip8 = CPU::new();
@@ -23,79 +108,356 @@ impl CPUWasm {
             .map_err(|_| ())
             .unwrap();
 
-        let ctx = canvas.get_context("2d")
-            .unwrap().unwrap()
+        let ctx = canvas
+            .get_context("2d")
+            .unwrap()
+            .unwrap()
             .dyn_into::<CanvasRenderingContext2d>()
             .unwrap();
 
-        Ok(CPUWasm{chip8, ctx})
+        Ok(CPUWasm {
+            chip8,
+            ctx,
+            beeper: None,
+            volume: DEFAULT_VOLUME,
+            pitch: DEFAULT_PITCH,
+            config: Config::default(),
+            rom_hash: None,
+            settings: Settings::default(),
+            info: None,
+            keymap: Keymap::default(),
+        })
     }
 
     #[wasm_bindgen]
-    pub fn tick(&mut self) {
-        self.chip8.tick();
+    pub fn tick(&mut self) -> Result<(), JsValue> {
+        self.chip8
+            .tick()
+            .map_err(|err| JsValue::from_str(&err.to_string()))
     }
 
+    // The buzzer sounds for the whole frame in which the sound timer is active
     #[wasm_bindgen]
     pub fn tick_timers(&mut self) {
+        self.play_sound();
         self.chip8.tick_timers();
     }
 
+    #[wasm_bindgen]
+    pub fn is_sound_active(&self) -> bool {
+        self.chip8.is_sound_active()
+    }
+
+    // The volume goes from 0 (muted) to 1
+    // Like the pitch and the platform, it is saved into the settings of the loaded game
+    #[wasm_bindgen]
+    pub fn set_volume(&mut self, volume: f32) {
+        self.apply_volume(volume);
+        self.remember(Settings {
+            volume: Some((volume * 100.0).round() as u32),
+            ..Settings::default()
+        });
+    }
+
+    // The pitch is in Hz
+    #[wasm_bindgen]
+    pub fn set_pitch(&mut self, pitch: f32) {
+        self.apply_pitch(pitch);
+        self.remember(Settings {
+            pitch: Some(pitch),
+            ..Settings::default()
+        });
+    }
+
+    #[wasm_bindgen]
+    pub fn volume(&self) -> f32 {
+        self.volume
+    }
+
+    #[wasm_bindgen]
+    pub fn pitch(&self) -> f32 {
+        self.pitch
+    }
+
     #[wasm_bindgen]
     pub fn reset(&mut self) {
         self.chip8.reset();
+        self.rom_hash = None;
+    }
+
+    // Changing the platform resets the CPU, so the game has to be loaded again
+    #[wasm_bindgen]
+    pub fn set_platform(&mut self, name: &str) -> Result<(), JsValue> {
+        let platform = name
+            .parse::<Platform>()
+            .map_err(|err| JsValue::from_str(&err))?;
+        self.chip8.set_platform(platform);
+        self.chip8.set_quirks(platform.quirks());
+        self.remember(Settings {
+            platform: Some(platform.to_string()),
+            ..Settings::default()
+        });
+        Ok(())
+    }
+
+    #[wasm_bindgen]
+    pub fn platform(&self) -> String {
+        self.chip8.platform().to_string()
+    }
+
+    // The settings of every game, as the TOML file read by the desktop frontend
+    #[wasm_bindgen]
+    pub fn load_config(&mut self, text: &str) -> Result<(), JsValue> {
+        self.config = Config::parse(text).map_err(|err| JsValue::from_str(&err))?;
+        Ok(())
+    }
+
+    #[wasm_bindgen]
+    pub fn config(&self) -> String {
+        self.config.to_toml()
+    }
+
+    // The title and controls of the loaded game, when it is in the ROM database
+    #[wasm_bindgen]
+    pub fn title(&self) -> Option<String> {
+        self.info.as_ref().map(|info| info.title.clone())
+    }
+
+    #[wasm_bindgen]
+    pub fn controls(&self) -> Option<String> {
+        self.info
+            .as_ref()
+            .map(|info| info.controls())
+            .filter(|controls| !controls.is_empty())
+    }
+
+    // Instructions to run per second, and colors of the pixels which are on and off, of the loaded game
+    #[wasm_bindgen]
+    pub fn ips(&self) -> u32 {
+        self.settings.ips.unwrap_or(DEFAULT_IPS)
+    }
+
+    #[wasm_bindgen]
+    pub fn foreground(&self) -> String {
+        self.settings
+            .foreground()
+            .map_or(DEFAULT_FOREGROUND.to_string(), css_color)
+    }
+
+    #[wasm_bindgen]
+    pub fn background(&self) -> String {
+        self.settings
+            .background()
+            .map_or(DEFAULT_BACKGROUND.to_string(), css_color)
+    }
+
+    // The keys are matched by their position on the keyboard, whatever its layout
+    // Returns whether the key is bound, so that its default action (such as scrolling) can be prevented
+    #[wasm_bindgen]
+    pub fn keypress(&mut self, evt: KeyboardEvent, pressed: bool) -> bool {
+        match self.keymap.key(&evt.code()) {
+            Some(k) => {
+                self.chip8.keypress(k, pressed);
+                true
+            }
+            None => false,
+        }
     }
 
+    // The keymap presets, the first one being the default one
     #[wasm_bindgen]
-    pub fn keypress(&mut self, evt: KeyboardEvent, pressed: bool) {
-        let key = evt.key();
-        if let Some(k) = key2btn(&key) {
-            self.chip8.keypress(k, pressed);
+    pub fn keymap_presets() -> Vec<String> {
+        Keymap::PRESETS
+            .iter()
+            .map(|name| name.to_string())
+            .collect()
+    }
+
+    // The preset is saved for the loaded game, and the keys bound on top of it in the settings are kept
+    #[wasm_bindgen]
+    pub fn set_keymap(&mut self, name: &str) -> Result<(), JsValue> {
+        name.parse::<Keymap>()
+            .map_err(|err| JsValue::from_str(&err))?;
+        let changes = Settings {
+            keymap: Some(name.to_string()),
+            ..Settings::default()
+        };
+        self.keymap = self.settings.merge(&changes).keymap();
+        self.remember(changes);
+        Ok(())
+    }
+
+    #[wasm_bindgen]
+    pub fn keymap(&self) -> String {
+        self.settings
+            .keymap
+            .clone()
+            .unwrap_or_else(|| Keymap::PRESETS[0].to_string())
+    }
+
+    // The saved settings of the game replace the current ones, starting with its platform
+    // Fails if the ROM doesn't fit in the RAM of the platform
+    #[wasm_bindgen]
+    pub fn load_game(&mut self, data: Uint8Array) -> Result<(), JsValue> {
+        let rom = data.to_vec();
+        let rom_hash = config::hash_rom(&rom);
+        self.settings = self.config.settings(&rom_hash);
+        self.info = database::lookup(&rom_hash);
+        self.keymap = self.settings.keymap();
+        if let Some(platform) = self.settings.platform() {
+            self.chip8.set_platform(platform);
+            self.chip8.set_quirks(platform.quirks());
         }
+        if let Some(quirks) = self.settings.quirks() {
+            self.chip8.set_quirks(quirks);
+        }
+        if let Some(volume) = self.settings.volume {
+            self.apply_volume(volume as f32 / 100.0);
+        }
+        if let Some(pitch) = self.settings.pitch {
+            self.apply_pitch(pitch);
+        }
+        self.chip8
+            .load(&rom)
+            .map_err(|err| JsValue::from_str(&err.to_string()))?;
+        self.rom_hash = Some(rom_hash);
+        Ok(())
+    }
+
+    // Read-only access to the registers and the RAM, for the tools written in JavaScript
+    // The arrays are copies, so they don't change as the program runs
+    #[wasm_bindgen]
+    pub fn pc(&self) -> u16 {
+        self.chip8.pc()
+    }
+
+    #[wasm_bindgen]
+    pub fn i_reg(&self) -> u16 {
+        self.chip8.i_reg()
+    }
+
+    #[wasm_bindgen]
+    pub fn v_reg(&self) -> Vec<u8> {
+        self.chip8.v_reg().to_vec()
+    }
+
+    #[wasm_bindgen]
+    pub fn sp(&self) -> u16 {
+        self.chip8.sp()
+    }
+
+    #[wasm_bindgen]
+    pub fn stack(&self) -> Vec<u16> {
+        self.chip8.stack().to_vec()
+    }
+
+    #[wasm_bindgen]
+    pub fn dt(&self) -> u8 {
+        self.chip8.dt()
+    }
+
+    #[wasm_bindgen]
+    pub fn st(&self) -> u8 {
+        self.chip8.st()
+    }
+
+    #[wasm_bindgen]
+    pub fn is_halted(&self) -> bool {
+        self.chip8.is_halted()
+    }
+
+    // All of the above at once, along with the keys, the resolution and the planes: an object with the fields of
+    // `CpuState`, whose arrays are plain JavaScript arrays
+    #[wasm_bindgen]
+    pub fn cpu_state(&self) -> Result<JsValue, JsValue> {
+        Ok(serde_wasm_bindgen::to_value(&self.chip8.cpu_state())?)
+    }
+
+    // Returns undefined when the address is past the end of the RAM
+    #[wasm_bindgen]
+    pub fn peek(&self, addr: usize) -> Option<u8> {
+        self.chip8.peek(addr)
+    }
+
+    #[wasm_bindgen]
+    pub fn memory(&self) -> Vec<u8> {
+        self.chip8.memory().to_vec()
+    }
+
+    // Changes to the registers and the RAM, for the debuggers
+    #[wasm_bindgen]
+    pub fn set_pc(&mut self, pc: u16) {
+        self.chip8.set_pc(pc);
+    }
+
+    #[wasm_bindgen]
+    pub fn set_i_reg(&mut self, i: u16) {
+        self.chip8.set_i_reg(i);
     }
 
     #[wasm_bindgen]
-    pub fn load_game(&mut self, data: Uint8Array) {
-        self.chip8.load(&data.to_vec());
+    pub fn set_v_reg(&mut self, x: usize, value: u8) {
+        self.chip8.set_v_reg(x, value);
     }
 
     #[wasm_bindgen]
-    pub fn draw_screen(&mut self, scale: usize) {
+    pub fn set_dt(&mut self, dt: u8) {
+        self.chip8.set_dt(dt);
+    }
+
+    #[wasm_bindgen]
+    pub fn set_st(&mut self, st: u8) {
+        self.chip8.set_st(st);
+    }
+
+    #[wasm_bindgen]
+    pub fn write_memory(&mut self, addr: usize, value: u8) -> Result<(), JsValue> {
+        self.chip8
+            .write_memory(addr, value)
+            .map_err(|err| JsValue::from_str(&err.to_string()))
+    }
+
+    // Draws the pixels of the given color (from 1 to 3) with the current fill style of the canvas
+    // Colors 2 and 3 are only used by the XO-CHIP programs drawing on the second plane
+    #[wasm_bindgen]
+    pub fn draw_screen(&mut self, scale: usize, color: u8) {
         let disp = self.chip8.get_display();
-        for i in 0..(SCREEN_WIDTH * SCREEN_HEIGHT) {
-            if disp[i] {
-                let x = i % SCREEN_WIDTH;
-                let y = i / SCREEN_WIDTH;
-                self.ctx.fill_rect(
-                    (x * scale) as f64,
-                    (y * scale) as f64,
-                    scale as f64,
-                    scale as f64
-                );
+        // The scale is given for the lo-res screen, so that the canvas keeps its size in the hi-res mode
+        let pixel = (scale * SCREEN_WIDTH) as f64 / disp.width as f64;
+        for (i, &c) in disp.pixels.iter().enumerate() {
+            if c == color {
+                let x = i % disp.width;
+                let y = i / disp.width;
+                self.ctx
+                    .fill_rect(x as f64 * pixel, y as f64 * pixel, pixel, pixel);
             }
         }
     }
 }
 
+impl CPUWasm {
+    // The audio context is only created once the buzzer first sounds, as browsers only allow it after the user has
+    // interacted with the page, which they did by picking the game
+    // If it can't be created, the game is silent
+    // Nothing is queued while the buzzer is silent
+    fn play_sound(&mut self) {
+        if !self.chip8.is_sound_active() {
+            return;
+        }
+        if self.beeper.is_none() {
+            self.beeper = Beeper::new().ok();
+            self.apply_volume(self.volume);
+            self.apply_pitch(self.pitch);
+        }
+        if let Some(beeper) = &mut self.beeper {
+            beeper.audio.update(&self.chip8);
+            if beeper.play_frame().is_err() {
+                self.beeper = None;
+            }
+        }
+    }
+}
 
-fn key2btn(key: &str) -> Option<usize> {
-    match key {
-        "1" => Some(0x1),
-        "2" => Some(0x2),
-        "3" => Some(0x3),
-        "4" => Some(0xC),
-        "q" => Some(0x4),
-        "w" => Some(0x5),
-        "e" => Some(0x6),
-        "r" => Some(0xD),
-        "a" => Some(0x7),
-        "s" => Some(0x8),
-        "d" => Some(0x9),
-        "f" => Some(0xE),
-        "z" => Some(0xA),
-        "x" => Some(0x0),
-        "c" => Some(0xB),
-        "v" => Some(0xF),
-        _ =>   None,
-    }
-}
\ No newline at end of file
+fn css_color([r, g, b]: [u8; 3]) -> String {
+    format!("#{:02X}{:02X}{:02X}", r, g, b)
+}
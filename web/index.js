@@ -3,8 +3,16 @@ import init, * as wasm from "./wasm.js";
 const WIDTH = 64;
 const HEIGHT = 32;
 const SCALE = 15;
-const TICKS_PER_FRAME = 10;
+const FRAMES_PER_SECOND = 60;
+// The settings of every game are kept in the local storage of the browser, in the format of the desktop config file
+const CONFIG_KEY = "chip8-config";
+// Colors of the pixels, indexed by the XO-CHIP planes they are set on
+// The first two are replaced by the colors of the game, if it has some
+const PALETTE = ["black", "white", "#AAAAAA", "#555555"];
 let anim_frame = 0;
+// Instructions run per second, and instructions left over from the previous frames in sixtieths of an instruction
+let ips = 600;
+let tick_budget = 0;
 
 const canvas = document.getElementById("canvas");
 canvas.width = WIDTH * SCALE;
@@ -15,27 +23,108 @@ ctx.fillStyle = "black";
 ctx.fillRect(0, 0, WIDTH * SCALE, HEIGHT * SCALE);
 
 const input = document.getElementById("fileinput");
+const platform = document.getElementById("platform");
+const keymap = document.getElementById("keymap");
+const volume = document.getElementById("volume");
+const pitch = document.getElementById("pitch");
+const info = document.getElementById("info");
 
 async function run() {
 	await init();
 	let chip8 = new wasm.CPUWasm();
+	try {
+		chip8.load_config(localStorage.getItem(CONFIG_KEY) || "");
+	} catch (err) {
+		console.error("Ignoring the saved settings: " + err);
+	}
+	const saveConfig = function () {
+		localStorage.setItem(CONFIG_KEY, chip8.config());
+	};
+	let rom = null;
 
+	// The bound keys don't scroll the page or fill in the inputs
 	document.addEventListener("keydown", function (evt) {
-		chip8.keypress(evt, true);
+		if (chip8.keypress(evt, true)) {
+			evt.preventDefault();
+		}
 	});
 
 	document.addEventListener("keyup", function (evt) {
-		chip8.keypress(evt, false);
+		if (chip8.keypress(evt, false)) {
+			evt.preventDefault();
+		}
+	});
+
+	// The keymap preset is saved for the game being played
+	for (const name of wasm.CPUWasm.keymap_presets()) {
+		keymap.add(new Option(name, name));
+	}
+	keymap.addEventListener("change", function () {
+		chip8.set_keymap(keymap.value);
+		saveConfig();
+	});
+
+	// The volume slider goes from 0 to 100, and the pitch is in Hz
+	// Each of them only saves its own setting, and a pitch which isn't a number is put back as it was
+	volume.value = Math.round(chip8.volume() * 100);
+	pitch.value = chip8.pitch();
+	volume.addEventListener("input", function () {
+		chip8.set_volume(volume.value / 100);
+		saveConfig();
+	});
+	pitch.addEventListener("change", function () {
+		const hz = Number(pitch.value);
+		if (pitch.value === "" || !(hz > 0)) {
+			pitch.value = chip8.pitch();
+			return;
+		}
+		chip8.set_pitch(hz);
+		saveConfig();
+	});
+
+	// Start the game over with the platform of its settings, or the selected one, and show its settings
+	const start = function () {
+		// Stop previous game from rendering, if one exists
+		if (anim_frame != 0) {
+			window.cancelAnimationFrame(anim_frame);
+		}
+		anim_frame = 0;
+		chip8.reset();
+		chip8.set_platform(platform.value);
+		// A ROM too large for the RAM of the platform can't be played
+		try {
+			chip8.load_game(rom);
+		} catch (err) {
+			alert("Unable to load the game: " + err);
+			return;
+		}
+		platform.value = chip8.platform();
+		keymap.value = chip8.keymap();
+		volume.value = Math.round(chip8.volume() * 100);
+		pitch.value = chip8.pitch();
+		PALETTE[0] = chip8.background();
+		PALETTE[1] = chip8.foreground();
+		ips = chip8.ips();
+		// The games of the ROM database are shown with their title and controls
+		const title = chip8.title();
+		const controls = chip8.controls();
+		info.textContent = title ? title + (controls ? " - Controls: " + controls : "") : "";
+		tick_budget = 0;
+		mainloop(chip8);
+	};
+
+	// The platform is saved for the game being played, which then starts over
+	platform.addEventListener("change", function () {
+		if (rom) {
+			chip8.set_platform(platform.value);
+			saveConfig();
+			start();
+		}
 	});
 
 	input.addEventListener(
 		"change",
 		function (evt) {
-			// Stop previous game from rendering, if one exists
-			if (anim_frame != 0) {
-				window.cancelAnimationFrame(anim_frame);
-			}
-
 			let file = evt.target.files[0];
 			if (!file) {
 				alert("Failed to read file");
@@ -46,10 +135,8 @@ async function run() {
 			let fr = new FileReader();
 			fr.onload = function (e) {
 				let buffer = fr.result;
-				const rom = new Uint8Array(buffer);
-				chip8.reset();
-				chip8.load_game(rom);
-				mainloop(chip8);
+				rom = new Uint8Array(buffer);
+				start();
 			};
 			fr.readAsArrayBuffer(file);
 		},
@@ -59,17 +146,29 @@ async function run() {
 
 function mainloop(chip8) {
 	// Only draw every few ticks
-	for (let i = 0; i < TICKS_PER_FRAME; i++) {
-		chip8.tick();
+	// If the game runs into an invalid instruction, stop the emulation and report the error
+	try {
+		tick_budget += ips;
+		const ticks = Math.floor(tick_budget / FRAMES_PER_SECOND);
+		tick_budget %= FRAMES_PER_SECOND;
+		for (let i = 0; i < ticks; i++) {
+			chip8.tick();
+		}
+	} catch (err) {
+		anim_frame = 0;
+		alert("Emulation stopped: " + err);
+		return;
 	}
 	chip8.tick_timers();
 
 	// Clear the canvas before drawing
-	ctx.fillStyle = "black";
+	ctx.fillStyle = PALETTE[0];
 	ctx.fillRect(0, 0, WIDTH * SCALE, HEIGHT * SCALE);
-	// Set the draw color back to white before we render our frame
-	ctx.fillStyle = "white";
-	chip8.draw_screen(SCALE);
+	// Render our frame, one color at a time
+	for (let color = 1; color < PALETTE.length; color++) {
+		ctx.fillStyle = PALETTE[color];
+		chip8.draw_screen(SCALE, color);
+	}
 
 	anim_frame = window.requestAnimationFrame(() => {
 		mainloop(chip8);